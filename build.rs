@@ -31,7 +31,60 @@ pub mod embedded {
     include!("src/embedded.rs");
 }
 
+/// Runs git with the given arguments, returning its trimmed output, or `None`
+/// if git is not available or the package is not built from a git repository
+fn git(args: &[&str]) -> Option<String> {
+    std::process::Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_owned())
+}
+
 fn main() -> Result<(), configure_me_codegen::Error> {
+    let git_hash =
+        git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| s!("unknown"));
+    println!("cargo:rustc-env=MYCITADEL_GIT_HASH={}", git_hash);
+
+    // Once any `rerun-if-changed` is given cargo stops watching the package
+    // files, so the sources included above must be listed explicitly
+    for file in &[
+        "build.rs",
+        "src/shared.rs",
+        "src/cli/opts.rs",
+        "src/daemon.rs",
+        "src/embedded.rs",
+    ] {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+    // Git hash must be updated on each commit and branch switch. Git files are
+    // located with git itself, since `.git` may be a file (worktrees,
+    // submodules) or reside outside of the package directory
+    if let (Some(git_dir), Some(common_dir)) = (
+        git(&["rev-parse", "--git-dir"]),
+        git(&["rev-parse", "--git-common-dir"]),
+    ) {
+        let git_dir = std::path::Path::new(&git_dir);
+        let common_dir = std::path::Path::new(&common_dir);
+        let head = git_dir.join("HEAD");
+        println!("cargo:rerun-if-changed={}", head.display());
+        if let Some(head_ref) = std::fs::read_to_string(&head)
+            .ok()
+            .and_then(|head| head.strip_prefix("ref: ").map(str::to_owned))
+        {
+            println!(
+                "cargo:rerun-if-changed={}",
+                common_dir.join(head_ref.trim()).display()
+            );
+            println!(
+                "cargo:rerun-if-changed={}",
+                common_dir.join("packed-refs").display()
+            );
+        }
+    }
+
     let outdir = "./shell";
 
     for app in [
@@ -30,6 +30,8 @@ extern crate log;
 
 use clap::Clap;
 use colored::Colorize;
+use std::convert::TryFrom;
+use std::process;
 
 use citadel::client::{Client, Config};
 use citadel::Error;
@@ -41,15 +43,17 @@ fn main() {
     LogLevel::from_verbosity_flag_count(opts.shared.verbose).apply();
     trace!("Command-line arguments: {:#?}", &opts);
 
-    let config: Config = opts.clone().into();
+    let config = Config::try_from(opts.clone()).unwrap_or_else(|err| {
+        eprintln!("{} {}", "Error:".bright_red(), err.red());
+        process::exit(1)
+    });
     trace!("Tool configuration: {:#?}", &config);
 
     let mut client = Client::with(config).expect("Error initializing client");
 
     trace!("Executing command: {}", opts.command);
-    opts.command
-        .exec(&mut client)
-        .unwrap_or_else(|err| match err {
+    opts.exec(&mut client).unwrap_or_else(|err| {
+        match err {
             Error::ServerFailure(_) => {}
             err => {
                 eprintln!(
@@ -58,5 +62,7 @@ fn main() {
                     err.to_string().replace(": ", "\n  > ").red()
                 )
             }
-        });
+        }
+        process::exit(1)
+    });
 }
@@ -40,9 +40,9 @@ fn main() -> Result<(), Error> {
 
     trace!("Command-line arguments: {:#?}", &opts);
 
-    let config = runtime::Config::from(opts.daemon);
+    let config = runtime::Config::from(opts.daemon.clone());
 
     let mut client =
         citadel::run_embedded(config).expect("Error initializing Citadel");
-    opts.command.exec(&mut client)
+    opts.exec(&mut client)
 }
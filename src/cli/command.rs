@@ -12,31 +12,42 @@
 // If not, see <https://www.gnu.org/licenses/agpl-3.0-standalone.html>.
 
 use colored::Colorize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::FromStr;
 use std::{fs, io};
 
-use bitcoin::consensus::{deserialize, serialize};
+use amplify::Wrapper;
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::{sha256t, Hash};
 use bitcoin::util::bip32::ExtendedPrivKey;
+use bitcoin::{Address, Txid};
 use invoice::Invoice;
 use microservices::rpc::Failure;
 use microservices::shell::Exec;
 use rgb::{Consignment, Validity};
-use slip132::FromSlip132;
+use slip132::{FromSlip132, ToSlip132};
 use strict_encoding::StrictEncode;
-use wallet::hd::PubkeyChain;
-use wallet::psbt::{Psbt, Signer};
+use wallet::blockchain::BITCOIN_GENESIS_BLOCKHASH;
+use wallet::descriptors;
+use wallet::hd::{PubkeyChain, UnhardenedIndex, XpubRef};
+use wallet::psbt::Signer;
 
 use citadel::client::InvoiceType;
-use citadel::model::SpendingPolicy;
+use citadel::model::{
+    self, AddressDerivation, Operation, PaymentDirecton, SpendingPolicy,
+};
 use citadel::rpc::Reply;
 use citadel::{Client, Error, SECP256K1};
 
-use super::util;
+use super::opts::parse_wallet_name;
+use super::{output, util};
 use super::{
-    AddressCommand, AssetCommand, Command, InvoiceCommand, OutputFormat,
-    WalletCommand, WalletCreateCommand, WalletOpts,
+    AccountXpub, AddressCommand, AssetCommand, CoinAllocation, Command,
+    CosignerStatus, InvoiceCommand, Opts, OutputFormat, OutputOpts,
+    PaymentResult, PreciseOperation, PreciseUtxo, VersionInfo, WalletCommand,
+    WalletCreateCommand, WalletOpts,
 };
+use crate::EmbeddedOpts;
 
 const LOOKUP_DEPTH_DEFAULT: u8 = 20;
 
@@ -66,60 +77,379 @@ impl ReportError for Reply {
     }
 }
 
-impl Exec for Command {
+/// Reports an error detected on the client side and wraps it into the same
+/// error type used for server failures, which were already reported to the
+/// user
+fn report_failure(info: String) -> Error {
+    eprintln!("{} {}", "Error:".bright_red(), info.red());
+    Error::ServerFailure(Failure { code: 0, info })
+}
+
+/// Returns all operations stored in the wallet history
+fn wallet_operations(
+    client: &mut Client,
+    wallet_id: model::ContractId,
+) -> Result<Vec<Operation>, Error> {
+    client
+        .contract_operations(wallet_id)?
+        .report_error("retrieving wallet operations")
+        .and_then(|reply| match reply {
+            Reply::Contract(contract) => Ok(contract),
+            _ => Err(Error::UnexpectedApi),
+        })
+        .map(|contract| {
+            contract
+                .history()
+                .into_iter()
+                .map(|operation| operation.clone())
+                .collect()
+        })
+}
+
+/// Returns wallet operation performed by the transaction with the given id
+fn wallet_operation(
+    client: &mut Client,
+    wallet_id: model::ContractId,
+    txid: Txid,
+) -> Result<Operation, Error> {
+    wallet_operations(client, wallet_id)?
+        .into_iter()
+        .find(|operation| {
+            operation.psbt.as_inner().global.unsigned_tx.txid() == txid
+        })
+        .ok_or_else(|| {
+            report_failure(format!(
+                "wallet {} has no operation with transaction id {}",
+                wallet_id, txid
+            ))
+        })
+}
+
+/// Returns ticker and decimal precision for each of the known assets,
+/// including bitcoin
+fn asset_precisions(
+    client: &mut Client,
+) -> Result<HashMap<rgb::ContractId, (String, u8)>, Error> {
+    let mut precisions = client
+        .asset_list()?
+        .report_error("listing assets")
+        .and_then(|reply| match reply {
+            Reply::Assets(assets) => Ok(assets),
+            _ => Err(Error::UnexpectedApi),
+        })?
+        .into_iter()
+        .map(|asset| {
+            (
+                *asset.id(),
+                (asset.ticker().to_owned(), asset.decimal_precision()),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    precisions.insert(default!(), (s!("BTC"), 8));
+    precisions.insert(util::bitcoin_asset_id(), (s!("BTC"), 8));
+    Ok(precisions)
+}
+
+fn address_derive(
+    client: &mut Client,
+    wallet_id: model::ContractId,
+    index: u16,
+    mark_used: bool,
+    legacy: bool,
+) -> Result<AddressDerivation, Error> {
+    client
+        .address_create(
+            wallet_id,
+            Some(UnhardenedIndex::from(index)),
+            mark_used,
+            legacy,
+        )?
+        .report_error("generating address")
+        .and_then(|reply| match reply {
+            Reply::AddressDerivation(ad) => Ok(ad),
+            _ => Err(Error::UnexpectedApi),
+        })
+}
+
+fn single_sig_create(
+    client: &mut Client,
+    name: String,
+    pubkey_chain: PubkeyChain,
+    category: descriptors::ContentType,
+) -> Result<model::ContractId, Error> {
+    eprintln!(
+        "Creating single-sig {} wallet with public key generator {}",
+        category.to_string().yellow(),
+        pubkey_chain.to_string().yellow(),
+    );
+    client
+        .single_sig_create(name, pubkey_chain, category)?
+        .report_error("during wallet creation")
+        .and_then(|reply| match reply {
+            Reply::Contract(contract) => Ok(contract),
+            _ => Err(Error::UnexpectedApi),
+        })
+        .map(|contract| {
+            eprintln!(
+                "Wallet named '{}' was successfully created.\n\
+                Use the following string as the wallet id:",
+                contract.name().green()
+            );
+            println!("{}", contract.id().to_string().bright_green());
+            *contract.id()
+        })
+}
+
+impl Command {
+    /// Validates columns requested with `--columns` option against the output
+    /// of the command before it gets executed
+    fn check_columns(&self, output_opts: &OutputOpts) -> Result<(), String> {
+        match self {
+            Command::Version { .. } => {
+                output::check_columns::<VersionInfo>(output_opts)
+            }
+            Command::Wallet { subcommand } => match subcommand {
+                WalletCommand::List { .. } => {
+                    output::check_columns::<model::ContractMeta>(output_opts)
+                }
+                WalletCommand::Balance { .. } => output::check_columns::<
+                    BTreeMap<rgb::ContractId, Vec<PreciseUtxo>>,
+                >(output_opts),
+                WalletCommand::Coins { .. } => {
+                    output::check_columns::<CoinAllocation>(output_opts)
+                }
+                WalletCommand::Xpub { .. } => {
+                    output::check_columns::<AccountXpub>(output_opts)
+                }
+                WalletCommand::SigningStatus { .. } => {
+                    output::check_columns::<CosignerStatus>(output_opts)
+                }
+                WalletCommand::History { .. }
+                | WalletCommand::Operation { .. } => {
+                    output::check_columns::<PreciseOperation>(output_opts)
+                }
+                _ => Ok(()),
+            },
+            Command::Address { subcommand } => match subcommand {
+                AddressCommand::ListUsed { .. } => {
+                    output::check_columns::<HashMap<Address, UnhardenedIndex>>(
+                        output_opts,
+                    )
+                }
+                AddressCommand::Create { .. }
+                | AddressCommand::Batch { .. }
+                | AddressCommand::Derive { .. } => {
+                    output::check_columns::<AddressDerivation>(output_opts)
+                }
+                AddressCommand::Pay {
+                    summary: Some(_), ..
+                } => output::check_columns::<PaymentResult>(output_opts),
+                _ => Ok(()),
+            },
+            Command::Asset { subcommand } => match subcommand {
+                AssetCommand::List { .. } | AssetCommand::Info { .. } => {
+                    output::check_columns::<rgb20::Asset>(output_opts)
+                }
+                _ => Ok(()),
+            },
+            Command::Invoice { subcommand } => match subcommand {
+                InvoiceCommand::List { .. } | InvoiceCommand::Info { .. } => {
+                    output::check_columns::<Invoice>(output_opts)
+                }
+                InvoiceCommand::Pay {
+                    summary: Some(_), ..
+                } => output::check_columns::<PaymentResult>(output_opts),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl Exec for Opts {
     type Client = Client;
     type Error = Error;
 
     #[inline]
     fn exec(self, client: &mut Self::Client) -> Result<(), Self::Error> {
-        match self {
-            Command::Wallet { subcommand } => subcommand.exec(client),
-            Command::Asset { subcommand } => subcommand.exec(client),
-            Command::Address { subcommand } => subcommand.exec(client),
-            Command::Invoice { subcommand } => subcommand.exec(client),
-        }
+        self.command.exec(client, &self.output)
     }
 }
 
-impl Exec for WalletCommand {
+impl Exec for EmbeddedOpts {
     type Client = Client;
     type Error = Error;
 
+    #[inline]
     fn exec(self, client: &mut Self::Client) -> Result<(), Self::Error> {
+        self.command.exec(client, &self.output)
+    }
+}
+
+impl Command {
+    /// Executes the command, printing its output according to the provided
+    /// output options
+    pub fn exec(
+        self,
+        client: &mut Client,
+        output_opts: &OutputOpts,
+    ) -> Result<(), Error> {
+        self.check_columns(output_opts).map_err(report_failure)?;
+        match self {
+            Command::Wallet { subcommand } => {
+                subcommand.exec(client, output_opts)
+            }
+            Command::Asset { subcommand } => {
+                subcommand.exec(client, output_opts)
+            }
+            Command::Address { subcommand } => {
+                subcommand.exec(client, output_opts)
+            }
+            Command::Invoice { subcommand } => {
+                subcommand.exec(client, output_opts)
+            }
+            Command::Version { format } => {
+                VersionInfo::current()
+                    .output_print(format, output_opts)
+                    .map_err(report_failure)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl WalletCommand {
+    fn exec(
+        self,
+        client: &mut Client,
+        output_opts: &OutputOpts,
+    ) -> Result<(), Error> {
         match self {
             WalletCommand::Create {
                 subcommand:
                     WalletCreateCommand::SingleSig {
                         name,
                         pubkey_chain,
+                        hardware,
                         opts,
                     },
             } => {
-                let category = opts.descriptor_category();
-                eprintln!(
-                    "Creating single-sig {} wallet with public key generator {}",
-                    category.to_string().yellow(),
-                    pubkey_chain.to_string().yellow(),
-
-                );
-                client
-                    .single_sig_create(name, pubkey_chain, category)?
-                    .report_error("during wallet creation")
-                    .and_then(|reply| match reply {
-                        Reply::Contract(contract) => Ok(contract),
-                        _ => Err(Error::UnexpectedApi),
+                if hardware && pubkey_chain.master == XpubRef::Unknown {
+                    return Err(report_failure(s!(
+                        "hardware wallet requires key origin with the device \
+                         master fingerprint, like `m=[6734cda8]/84'/0'/0'=[xpub...]/0-1/*`; \
+                         bare extended public keys can't be matched to the \
+                         device during signing"
+                    )));
+                }
+                single_sig_create(
+                    client,
+                    name,
+                    pubkey_chain,
+                    opts.descriptor_category(),
+                )
+                .map(|_| ())
+            }
+            WalletCommand::Create {
+                subcommand:
+                    WalletCreateCommand::ImportDescriptor {
+                        name,
+                        descriptors,
+                        file,
+                    },
+            } => {
+                let descriptors = match file {
+                    None => descriptors,
+                    Some(file) => {
+                        let json: serde_json::Value = serde_json::from_str(
+                            &fs::read_to_string(&file)?,
+                        )
+                        .map_err(|err| report_failure(err.to_string()))?;
+                        json.get("descriptors")
+                            .unwrap_or(&json)
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|item| item.get("desc"))
+                            .filter_map(serde_json::Value::as_str)
+                            .map(str::to_owned)
+                            .collect()
+                    }
+                };
+                let accounts = util::core_descriptors_accounts(&descriptors)
+                    .map_err(report_failure)?
+                    .into_iter()
+                    .map(|account| {
+                        PubkeyChain::from_str(&account.pubkey_chain)
+                            .map(|pubkey_chain| (account.clone(), pubkey_chain))
+                            .map_err(|err| {
+                                report_failure(format!(
+                                    "unable to convert descriptor key `{}`: {}",
+                                    account.pubkey_chain, err
+                                ))
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Each account key becomes a separate wallet; names are
+                // suffixed with the account type and must be checked before
+                // any of the wallets gets created
+                let count = accounts.len();
+                let accounts = accounts
+                    .into_iter()
+                    .map(|(account, pubkey_chain)| {
+                        let name = match (count, account.nested) {
+                            (1, _) => name.clone(),
+                            (_, false) => {
+                                format!("{} ({})", name, account.category)
+                            }
+                            (_, true) => format!(
+                                "{} (nested {})",
+                                name, account.category
+                            ),
+                        };
+                        match parse_wallet_name(&name) {
+                            Ok(name) => Ok((name, account, pubkey_chain)),
+                            Err(err) => Err(report_failure(format!(
+                                "invalid name `{}` for {} account: {}; use \
+                                 shorter wallet name",
+                                name, account.category, err
+                            ))),
+                        }
                     })
-                    .map(|contract| {
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut created = vec![];
+                for (name, account, pubkey_chain) in accounts {
+                    match single_sig_create(
+                        client,
+                        name,
+                        pubkey_chain,
+                        account.category,
+                    ) {
+                        Ok(wallet_id) => created.push(wallet_id),
+                        Err(err) => {
+                            if !created.is_empty() {
+                                eprintln!(
+                                    "{} import failed after creating the \
+                                     following wallets: {}",
+                                    "Warning:".bright_yellow(),
+                                    created
+                                        .iter()
+                                        .map(model::ContractId::to_string)
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                );
+                            }
+                            return Err(err);
+                        }
+                    }
+                    if account.nested {
                         eprintln!(
-                            "Wallet named '{}' was successfully created.\n\
-                            Use the following string as the wallet id:",
-                            contract.name().green()
-                        );
-                        println!(
-                            "{}",
-                            contract.id().to_string().bright_green()
+                            "{} wallet uses segwit nested into P2SH; use \
+                             `--legacy` flag with address commands",
+                            "Note:".bright_yellow()
                         );
-                    })
+                    }
+                }
+                Ok(())
             }
             WalletCommand::List { format } => client
                 .contract_list()?
@@ -128,7 +458,11 @@ impl Exec for WalletCommand {
                     Reply::Contracts(contracts) => Ok(contracts),
                     _ => Err(Error::UnexpectedApi),
                 })
-                .map(|contracts| contracts.output_print(format)),
+                .and_then(|contracts| {
+                    contracts
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)
+                }),
             WalletCommand::Rename {
                 wallet_id,
                 new_name,
@@ -160,6 +494,7 @@ impl Exec for WalletCommand {
                         lookup_depth,
                         format,
                     },
+                asset,
             } => client
                 .contract_balance(
                     wallet_id,
@@ -171,9 +506,191 @@ impl Exec for WalletCommand {
                     Reply::ContractUnspent(unspent) => Ok(unspent),
                     _ => Err(Error::UnexpectedApi),
                 })
-                .map(|unspent| unspent.output_print(format)),
+                .and_then(|unspent| {
+                    let precisions = asset_precisions(client)?;
+                    unspent
+                        .into_iter()
+                        .filter(|(asset_id, _)| {
+                            asset.map(|id| id == *asset_id).unwrap_or(true)
+                        })
+                        .map(|(asset_id, utxos)| {
+                            let (ticker, precision) = precisions
+                                .get(&asset_id)
+                                .cloned()
+                                .unwrap_or((s!("?"), 0));
+                            let utxos = utxos
+                                .into_iter()
+                                .map(|utxo| {
+                                    PreciseUtxo::with(utxo, precision, &ticker)
+                                })
+                                .collect::<Vec<_>>();
+                            (asset_id, utxos)
+                        })
+                        .collect::<BTreeMap<_, _>>()
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)?;
+                    Ok(())
+                }),
+            WalletCommand::Total { rates, rescan } => {
+                let rates = util::parse_rates(&fs::read_to_string(&rates)?)
+                    .map_err(report_failure)?;
+                let precisions = asset_precisions(client)?;
+                let contracts = client
+                    .contract_list()?
+                    .report_error("listing wallets")
+                    .and_then(|reply| match reply {
+                        Reply::Contracts(contracts) => Ok(contracts),
+                        _ => Err(Error::UnexpectedApi),
+                    })?;
+
+                let mut balances = BTreeMap::<rgb::ContractId, u64>::new();
+                for contract in contracts {
+                    let unspent = client
+                        .contract_balance(
+                            *contract.id(),
+                            rescan,
+                            LOOKUP_DEPTH_DEFAULT,
+                        )?
+                        .report_error("retrieving wallet balance")
+                        .and_then(|reply| match reply {
+                            Reply::ContractUnspent(unspent) => Ok(unspent),
+                            _ => Err(Error::UnexpectedApi),
+                        })?;
+                    for (asset_id, utxos) in unspent {
+                        // Bitcoin may be reported under the default id
+                        let asset_id = if asset_id == default!() {
+                            util::bitcoin_asset_id()
+                        } else {
+                            asset_id
+                        };
+                        *balances.entry(asset_id).or_default() +=
+                            utxos.iter().map(|utxo| utxo.value).sum::<u64>();
+                    }
+                }
+
+                let mut total = 0f64;
+                for (asset_id, value) in balances {
+                    let (ticker, precision) = precisions
+                        .get(&asset_id)
+                        .cloned()
+                        .unwrap_or((s!("?"), 0));
+                    let amount = util::format_amount(value, precision, &ticker);
+                    match rates.get(&asset_id) {
+                        None => eprintln!(
+                            "{} {} ({}): no exchange rate provided, skipping",
+                            "Warning:".bright_yellow(),
+                            amount,
+                            asset_id
+                        ),
+                        Some(rate) => {
+                            let amount_value =
+                                value as f64 / 10f64.powi(precision as i32);
+                            eprintln!("{} x {}", amount, rate);
+                            total += amount_value * rate;
+                        }
+                    }
+                }
+                println!("{}", total);
+                Ok(())
+            }
+            WalletCommand::Coins {
+                scan_opts:
+                    WalletOpts {
+                        wallet_id,
+                        rescan,
+                        lookup_depth,
+                        format,
+                    },
+            } => client
+                .contract_balance(
+                    wallet_id,
+                    rescan,
+                    lookup_depth.unwrap_or(LOOKUP_DEPTH_DEFAULT),
+                )?
+                .report_error("retrieving wallet balance")
+                .and_then(|reply| match reply {
+                    Reply::ContractUnspent(unspent) => Ok(unspent),
+                    _ => Err(Error::UnexpectedApi),
+                })
+                .and_then(|unspent| {
+                    let precisions = asset_precisions(client)?;
+                    CoinAllocation::with_unspent(unspent, &precisions)
+                        .map_err(report_failure)?
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)?;
+                    Ok(())
+                }),
+            WalletCommand::Xpub {
+                wallet_id,
+                slip132,
+                format,
+            } => client
+                .contract_operations(wallet_id)?
+                .report_error("retrieving wallet details")
+                .and_then(|reply| match reply {
+                    Reply::Contract(contract) => Ok(contract),
+                    _ => Err(Error::UnexpectedApi),
+                })
+                .and_then(|contract| {
+                    contract
+                        .pubkeychains()
+                        .into_iter()
+                        .map(|pubkey_chain| {
+                            let xpub = pubkey_chain.branch_xpub;
+                            AccountXpub {
+                                fingerprint: pubkey_chain
+                                    .master_fingerprint()
+                                    .to_string(),
+                                xpub: match slip132 {
+                                    Some(application) => xpub
+                                        .to_slip132_string(
+                                            application,
+                                            xpub.network,
+                                        ),
+                                    None => xpub.to_string(),
+                                },
+                                pubkey_chain,
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)
+                }),
+            WalletCommand::Descriptor {
+                wallet_id,
+                checksum,
+            } => {
+                let contract = client
+                    .contract_operations(wallet_id)?
+                    .report_error("retrieving wallet details")
+                    .and_then(|reply| match reply {
+                        Reply::Contract(contract) => Ok(contract),
+                        _ => Err(Error::UnexpectedApi),
+                    })?;
+                let descriptors = util::bip380_descriptors(
+                    &contract.policy().to_string(),
+                    &contract.pubkeychains(),
+                )
+                .map_err(report_failure)?;
+                for descriptor in &descriptors {
+                    if !checksum {
+                        println!("{}", descriptor);
+                        continue;
+                    }
+                    let checksum = util::descriptor_checksum(descriptor)
+                        .ok_or_else(|| {
+                            report_failure(format!(
+                                "wallet descriptor `{}` contains characters \
+                                 not allowed by BIP-380",
+                                descriptor
+                            ))
+                        })?;
+                    println!("{}#{}", descriptor, checksum);
+                }
+                Ok(())
+            }
             WalletCommand::Sign { wallet_id, psbt } => {
-                let mut psbt: Psbt = deserialize(&base64::decode(&psbt)?)?;
+                let mut psbt = util::parse_psbt(&psbt)?;
                 let contract = client
                     .contract_operations(wallet_id)?
                     .report_error("retrieving wallet details")
@@ -181,6 +698,23 @@ impl Exec for WalletCommand {
                         Reply::Contract(contract) => Ok(contract),
                         _ => Err(Error::UnexpectedApi),
                     })?;
+                let fingerprints = contract
+                    .pubkeychains()
+                    .iter()
+                    .map(PubkeyChain::master_fingerprint)
+                    .collect::<HashSet<_>>();
+                if !psbt
+                    .inputs
+                    .iter()
+                    .flat_map(|input| input.bip32_derivation.values())
+                    .any(|(fingerprint, _)| fingerprints.contains(fingerprint))
+                {
+                    return Err(report_failure(format!(
+                        "this PSBT does not belong to wallet {}: none of its \
+                         inputs is derived from the wallet keys",
+                        wallet_id
+                    )));
+                }
                 let reader = io::stdin();
                 eprintln!("Please provide extended private keys for the following master fingerprints:");
                 for fingerprint in contract
@@ -208,6 +742,16 @@ impl Exec for WalletCommand {
                         }
                     };
                     if let Some(xpriv) = xpriv {
+                        let key_fingerprint = xpriv.fingerprint(&*SECP256K1);
+                        if key_fingerprint != fingerprint {
+                            eprintln!(
+                                "{} this key's fingerprint is {}, expected {} \
+                                 - it won't sign any inputs",
+                                "Warning:".bright_yellow(),
+                                key_fingerprint.to_string().yellow(),
+                                fingerprint.to_string().green()
+                            );
+                        }
                         let signatures = psbt
                             .sign(&*SECP256K1, xpriv, true)
                             .map_err(|err| {
@@ -219,12 +763,132 @@ impl Exec for WalletCommand {
                         eprintln!("Created {} signatures", signatures);
                     }
                 }
+                util::psbt_signing_report(
+                    &psbt,
+                    &contract.policy().to_string(),
+                );
                 eprintln!("{} ", "Signed PSBT:".bright_yellow());
                 println!("{}", base64::encode(serialize(&psbt)));
                 Ok(())
             }
+            WalletCommand::History {
+                wallet_id,
+                incoming,
+                outgoing,
+                asset,
+                format,
+            } => {
+                let bitcoin_id = rgb::ContractId::from_inner(
+                    sha256t::Hash::from_inner(BITCOIN_GENESIS_BLOCKHASH.into()),
+                );
+                let asset = asset.map(|asset_id| {
+                    Some(asset_id).filter(|asset_id| *asset_id != bitcoin_id)
+                });
+                let precisions = asset_precisions(client)?;
+                wallet_operations(client, wallet_id)?
+                    .into_iter()
+                    .filter(|operation| match operation.direction {
+                        PaymentDirecton::Incoming { .. } => !outgoing,
+                        PaymentDirecton::Outcoming { .. } => !incoming,
+                    })
+                    .filter(|operation| {
+                        asset
+                            .map(|asset_id| operation.asset_id == asset_id)
+                            .unwrap_or(true)
+                    })
+                    .map(|operation| {
+                        PreciseOperation::with(operation, &precisions)
+                    })
+                    .collect::<Vec<_>>()
+                    .output_print(format, output_opts)
+                    .map_err(report_failure)?;
+                Ok(())
+            }
+            WalletCommand::GetPsbt {
+                wallet_id,
+                txid,
+                output,
+                format,
+            } => {
+                let operation = wallet_operation(client, wallet_id, txid)?;
+                match operation.direction {
+                    PaymentDirecton::Incoming { .. } => eprintln!(
+                        "{} transaction {} is an incoming payment, which was \
+                         composed and published by the payer",
+                        "Warning:".bright_yellow(),
+                        txid.to_string().yellow()
+                    ),
+                    PaymentDirecton::Outcoming {
+                        published: true, ..
+                    } => eprintln!(
+                        "{} transaction {} is already published; the PSBT \
+                         must not be signed or published again",
+                        "Warning:".bright_yellow(),
+                        txid.to_string().yellow()
+                    ),
+                    PaymentDirecton::Outcoming { .. } => {}
+                }
+                util::psbt_output(operation.psbt.as_inner(), output, format)
+            }
+            WalletCommand::SigningStatus {
+                wallet_id,
+                txid,
+                format,
+            } => {
+                let contract = client
+                    .contract_operations(wallet_id)?
+                    .report_error("retrieving wallet details")
+                    .and_then(|reply| match reply {
+                        Reply::Contract(contract) => Ok(contract),
+                        _ => Err(Error::UnexpectedApi),
+                    })?;
+                let operation = wallet_operation(client, wallet_id, txid)?;
+                let fingerprints = contract
+                    .pubkeychains()
+                    .iter()
+                    .map(PubkeyChain::master_fingerprint)
+                    .collect::<Vec<_>>();
+                let status = CosignerStatus::with_psbt(
+                    operation.psbt.as_inner(),
+                    &fingerprints,
+                );
+                let signed = status
+                    .iter()
+                    .filter(|cosigner| cosigner.is_complete())
+                    .count();
+                match util::signature_threshold(&contract.policy().to_string())
+                {
+                    Some(threshold) => eprintln!(
+                        "{} of {} required cosigners have signed all their \
+                         inputs",
+                        signed.to_string().yellow(),
+                        threshold.to_string().yellow()
+                    ),
+                    None => eprintln!(
+                        "{} of {} cosigners have signed all their inputs",
+                        signed.to_string().yellow(),
+                        status.len().to_string().yellow()
+                    ),
+                }
+                status
+                    .output_print(format, output_opts)
+                    .map_err(report_failure)?;
+                Ok(())
+            }
+            WalletCommand::Operation {
+                wallet_id,
+                txid,
+                format,
+            } => {
+                let precisions = asset_precisions(client)?;
+                let operation = wallet_operation(client, wallet_id, txid)?;
+                PreciseOperation::with(operation, &precisions)
+                    .output_print(format, output_opts)
+                    .map_err(report_failure)?;
+                Ok(())
+            }
             WalletCommand::Publish { wallet_id, psbt } => {
-                let psbt: Psbt = deserialize(&base64::decode(&psbt)?)?;
+                let psbt = util::parse_psbt(&psbt)?;
                 let txid = client.finalize_publish_psbt(psbt)?;
                 eprintln!("Published transaction with id ");
                 println!("{}", txid.to_string().green());
@@ -234,11 +898,12 @@ impl Exec for WalletCommand {
     }
 }
 
-impl Exec for AddressCommand {
-    type Client = Client;
-    type Error = Error;
-
-    fn exec(self, client: &mut Self::Client) -> Result<(), Self::Error> {
+impl AddressCommand {
+    fn exec(
+        self,
+        client: &mut Client,
+        output_opts: &OutputOpts,
+    ) -> Result<(), Error> {
         match self {
             AddressCommand::ListUsed {
                 scan_opts:
@@ -259,11 +924,12 @@ impl Exec for AddressCommand {
                     Reply::Addresses(addresses) => Ok(addresses),
                     _ => Err(Error::UnexpectedApi),
                 })
-                .map(|addresses| {
+                .and_then(|addresses| {
                     addresses
                         .into_iter()
                         .collect::<HashMap<_, _>>()
-                        .output_print(format)
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)
                 }),
             AddressCommand::Create {
                 wallet_id,
@@ -278,10 +944,255 @@ impl Exec for AddressCommand {
                     Reply::AddressDerivation(ad) => Ok(ad),
                     _ => Err(Error::UnexpectedApi),
                 })
-                .map(|address_derivation| {
-                    address_derivation.output_print(format)
+                .and_then(|address_derivation| {
+                    address_derivation
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)
                 }),
+            AddressCommand::Batch {
+                wallet_id,
+                count,
+                mark_used,
+                legacy,
+                format,
+            } => {
+                let used = client
+                    .address_list(wallet_id, false, LOOKUP_DEPTH_DEFAULT)?
+                    .report_error("retrieving used addresses")
+                    .and_then(|reply| match reply {
+                        Reply::Addresses(addresses) => Ok(addresses),
+                        _ => Err(Error::UnexpectedApi),
+                    })?
+                    .into_iter()
+                    .map(|(address, _)| address)
+                    .collect::<HashSet<_>>();
+                let next_unused = client
+                    .address_create(wallet_id, None, false, legacy)?
+                    .report_error("generating address")
+                    .and_then(|reply| match reply {
+                        Reply::AddressDerivation(ad) => Ok(ad),
+                        _ => Err(Error::UnexpectedApi),
+                    })?;
+                let start = next_unused
+                    .derivation
+                    .last()
+                    .copied()
+                    .map(u32::from)
+                    .unwrap_or_default();
+
+                let mut addresses = Vec::with_capacity(count as usize);
+                for index in start..=u16::MAX as u32 {
+                    if addresses.len() >= count as usize {
+                        break;
+                    }
+                    let derivation = address_derive(
+                        client,
+                        wallet_id,
+                        index as u16,
+                        false,
+                        legacy,
+                    )?;
+                    if used.contains(&derivation.address) {
+                        continue;
+                    }
+                    addresses.push(if mark_used {
+                        address_derive(
+                            client,
+                            wallet_id,
+                            index as u16,
+                            true,
+                            legacy,
+                        )?
+                    } else {
+                        derivation
+                    });
+                }
+                addresses
+                    .output_print(format, output_opts)
+                    .map_err(report_failure)?;
+                Ok(())
+            }
+            AddressCommand::Derive {
+                wallet_id,
+                from,
+                to,
+                legacy,
+                format,
+            } => {
+                if from > to {
+                    return Err(report_failure(format!(
+                        "invalid derivation range {}..{}",
+                        from, to
+                    )));
+                }
+                (from..=to)
+                    .map(|index| {
+                        address_derive(client, wallet_id, index, false, legacy)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+                    .output_print(format, output_opts)
+                    .map_err(report_failure)?;
+                Ok(())
+            }
+            AddressCommand::ImportUsed {
+                wallet_id,
+                file,
+                legacy,
+            } => {
+                let mut derivations = Vec::new();
+                for (no, line) in fs::read_to_string(&file)?.lines().enumerate()
+                {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let parsed = line
+                        .split_once(|c: char| c == ',' || c.is_whitespace())
+                        .and_then(|(index, address)| {
+                            Some((
+                                index.trim().parse::<u16>().ok()?,
+                                Address::from_str(address.trim()).ok()?,
+                            ))
+                        });
+                    match parsed {
+                        Some(derivation) => derivations.push(derivation),
+                        None => {
+                            return Err(report_failure(format!(
+                                "line {} of {}: expected derivation index and \
+                                 address",
+                                no + 1,
+                                file.display()
+                            )))
+                        }
+                    }
+                }
+
+                for (index, address) in &derivations {
+                    let derived = address_derive(
+                        client, wallet_id, *index, false, legacy,
+                    )?;
+                    if derived.address != *address {
+                        return Err(report_failure(format!(
+                            "address {} does not match wallet address {} at \
+                             derivation index {}",
+                            address, derived.address, index
+                        )));
+                    }
+                }
+                for (index, _) in &derivations {
+                    address_derive(client, wallet_id, *index, true, legacy)?;
+                }
+                eprintln!(
+                    "{} addresses were marked as used",
+                    derivations.len().to_string().bright_green()
+                );
+                Ok(())
+            }
+            AddressCommand::Faucet {
+                wallet_id,
+                faucet_url,
+                legacy,
+            } => {
+                // The address is marked as used only once the faucet has
+                // accepted the request
+                let derivation = client
+                    .address_create(wallet_id, None, false, legacy)?
+                    .report_error("generating address")
+                    .and_then(|reply| match reply {
+                        Reply::AddressDerivation(ad) => Ok(ad),
+                        _ => Err(Error::UnexpectedApi),
+                    })?;
+                let address = derivation.address;
+                if address.network == bitcoin::Network::Bitcoin {
+                    eprintln!(
+                        "{} faucets are not available for mainnet wallets",
+                        "Warning:".bright_yellow()
+                    );
+                    return Ok(());
+                }
+                eprintln!(
+                    "Requesting coins from {} to address {}",
+                    faucet_url.yellow(),
+                    address.to_string().yellow()
+                );
+                let response = ureq::post(&faucet_url)
+                    .send_form(&[("address", &address.to_string())])
+                    .map_err(|err| report_failure(err.to_string()))?
+                    .into_string()?;
+                let index = derivation
+                    .derivation
+                    .last()
+                    .copied()
+                    .map(u32::from)
+                    .unwrap_or_default();
+                address_derive(client, wallet_id, index as u16, true, legacy)?;
+                eprintln!("{}", "Faucet response:".bright_yellow());
+                println!("{}", response);
+                Ok(())
+            }
             AddressCommand::MarkUsed { .. } => unimplemented!(),
+            AddressCommand::Verify {
+                wallet_id,
+                address,
+                lookup_depth,
+                legacy,
+            } => {
+                let lookup_depth =
+                    lookup_depth.unwrap_or(LOOKUP_DEPTH_DEFAULT) as u16;
+                let used = client
+                    .address_list(wallet_id, false, lookup_depth as u8)?
+                    .report_error("retrieving used addresses")
+                    .and_then(|reply| match reply {
+                        Reply::Addresses(addresses) => Ok(addresses),
+                        _ => Err(Error::UnexpectedApi),
+                    })?
+                    .into_iter()
+                    .collect::<HashMap<_, _>>();
+
+                if let Some(index) = used.get(&address) {
+                    eprintln!(
+                        "Address {} belongs to wallet {} and was used at \
+                         derivation index",
+                        address.to_string().yellow(),
+                        wallet_id.to_string().yellow(),
+                    );
+                    println!("{}", index.to_string().bright_green());
+                    return Ok(());
+                }
+
+                let used = used.keys().collect::<HashSet<_>>();
+                let mut gap = 0u16;
+                let mut index = 0u16;
+                while gap <= lookup_depth {
+                    let derivation = address_derive(
+                        client, wallet_id, index, false, legacy,
+                    )?;
+                    if derivation.address == address {
+                        eprintln!(
+                            "Address {} belongs to wallet {} at derivation \
+                             index",
+                            address.to_string().yellow(),
+                            wallet_id.to_string().yellow(),
+                        );
+                        println!("{}", index.to_string().bright_green());
+                        return Ok(());
+                    }
+                    if used.contains(&derivation.address) {
+                        gap = 0;
+                    } else {
+                        gap += 1;
+                    }
+                    index = match index.checked_add(1) {
+                        Some(index) => index,
+                        None => break,
+                    };
+                }
+                Err(report_failure(format!(
+                    "address {} does not belong to wallet {} (checked first {} \
+                     derivation indexes)",
+                    address, wallet_id, index
+                )))
+            }
             AddressCommand::Pay {
                 address,
                 amount,
@@ -289,22 +1200,33 @@ impl Exec for AddressCommand {
                 pay_from,
                 output,
                 format,
+                summary,
             } => {
-                let invoice =
-                    Invoice::with_address(address, Some(amount.as_sat()));
+                let amount =
+                    amount.atomic_value(8, "BTC").map_err(report_failure)?;
+                let invoice = Invoice::with_address(address, Some(amount));
                 let prepared_payment =
                     client.invoice_pay(pay_from, invoice, None, fee, None)?;
-                util::psbt_output(&prepared_payment.psbt, output, format)
+                if summary.is_none() || output.is_some() {
+                    util::psbt_output(&prepared_payment.psbt, output, format)?;
+                }
+                if let Some(summary) = summary {
+                    PaymentResult::with(&prepared_payment.psbt, None)
+                        .output_print(summary, output_opts)
+                        .map_err(report_failure)?;
+                }
+                Ok(())
             }
         }
     }
 }
 
-impl Exec for AssetCommand {
-    type Client = Client;
-    type Error = Error;
-
-    fn exec(self, client: &mut Self::Client) -> Result<(), Self::Error> {
+impl AssetCommand {
+    fn exec(
+        self,
+        client: &mut Client,
+        output_opts: &OutputOpts,
+    ) -> Result<(), Error> {
         match self {
             AssetCommand::List { format } => client
                 .asset_list()?
@@ -313,9 +1235,48 @@ impl Exec for AssetCommand {
                     Reply::Assets(assets) => Ok(assets),
                     _ => Err(Error::UnexpectedApi),
                 })
-                .map(|assets| assets.output_print(format)),
-            AssetCommand::Import { genesis } => client
-                .asset_import(genesis)?
+                .and_then(|assets| {
+                    assets
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)
+                }),
+            AssetCommand::Info { asset_id, format } => client
+                .asset_list()?
+                .report_error("retrieving asset information")
+                .and_then(|reply| match reply {
+                    Reply::Assets(assets) => Ok(assets),
+                    _ => Err(Error::UnexpectedApi),
+                })
+                .and_then(|assets| {
+                    assets.into_iter().find(|a| *a.id() == asset_id).ok_or_else(
+                        || {
+                            report_failure(format!(
+                                "asset {} is not known; import its genesis \
+                                 first",
+                                asset_id
+                            ))
+                        },
+                    )
+                })
+                .and_then(|asset| {
+                    asset
+                        .output_print(format, output_opts)
+                        .map_err(report_failure)
+                }),
+            AssetCommand::Import { genesis, file } => client
+                .asset_import(match (genesis, file) {
+                    (_, Some(filename)) => {
+                        let data = fs::read(&filename)?;
+                        util::parse_consignment(&data)
+                            .map_err(report_failure)?
+                            .genesis
+                            .to_string()
+                    }
+                    (Some(genesis), None) => genesis,
+                    (None, None) => unreachable!(
+                        "clap ensures either genesis or file is present"
+                    ),
+                })?
                 .report_error("importing asset")
                 .and_then(|reply| match reply {
                     Reply::Asset(asset) => Ok(asset),
@@ -333,11 +1294,12 @@ impl Exec for AssetCommand {
     }
 }
 
-impl Exec for InvoiceCommand {
-    type Client = Client;
-    type Error = Error;
-
-    fn exec(self, client: &mut Self::Client) -> Result<(), Self::Error> {
+impl InvoiceCommand {
+    fn exec(
+        self,
+        client: &mut Client,
+        output_opts: &OutputOpts,
+    ) -> Result<(), Error> {
         match self {
             InvoiceCommand::Create {
                 wallet_id,
@@ -350,7 +1312,14 @@ impl Exec for InvoiceCommand {
                 descriptor,
                 psbt,
             } => {
-                // TODO: Check that asset id is known
+                if let Some(asset_id) = asset_id {
+                    if !asset_precisions(client)?.contains_key(&asset_id) {
+                        return Err(report_failure(format!(
+                            "unknown asset {}; import its genesis first",
+                            asset_id
+                        )));
+                    }
+                }
                 client
                     .invoice_create(
                         if descriptor {
@@ -383,10 +1352,13 @@ impl Exec for InvoiceCommand {
                     Reply::Invoices(list) => Ok(list),
                     _ => Err(Error::UnexpectedApi),
                 })
-                .map(|list| list.output_print(format)),
-            InvoiceCommand::Info { invoice, format } => {
-                Ok(invoice.output_print(format))
-            }
+                .and_then(|list| {
+                    list.output_print(format, output_opts)
+                        .map_err(report_failure)
+                }),
+            InvoiceCommand::Info { invoice, format } => invoice
+                .output_print(format, output_opts)
+                .map_err(report_failure),
             InvoiceCommand::Pay {
                 invoice,
                 wallet_id,
@@ -396,12 +1368,54 @@ impl Exec for InvoiceCommand {
                 consignment: consignment_file,
                 format,
                 giveaway,
+                summary,
             } => {
+                let amount = match amount {
+                    None => None,
+                    Some(amount) => {
+                        let (ticker, precision) = match invoice.asset() {
+                            None => (s!("BTC"), 8),
+                            Some(asset_id) => {
+                                let asset_id = rgb::ContractId::from_inner(
+                                    sha256t::Hash::from_inner(
+                                        asset_id.into_inner(),
+                                    ),
+                                );
+                                asset_precisions(client)?
+                                    .remove(&asset_id)
+                                    .ok_or_else(|| {
+                                        report_failure(format!(
+                                            "unknown asset {}",
+                                            asset_id
+                                        ))
+                                    })?
+                            }
+                        };
+                        Some(
+                            amount
+                                .atomic_value(precision, &ticker)
+                                .map_err(report_failure)?,
+                        )
+                    }
+                };
+                util::check_giveaway(
+                    invoice.beneficiary(),
+                    invoice.asset().map(|asset_id| {
+                        rgb::ContractId::from_inner(sha256t::Hash::from_inner(
+                            asset_id.into_inner(),
+                        ))
+                    }),
+                    giveaway,
+                )
+                .map_err(report_failure)?;
                 let prepared_payment = client
                     .invoice_pay(wallet_id, invoice, amount, fee, giveaway)?;
-                util::psbt_output(&prepared_payment.psbt, output, format)?;
-                if let Some(consignment) = prepared_payment.consignment {
+                if summary.is_none() || output.is_some() {
+                    util::psbt_output(&prepared_payment.psbt, output, format)?;
+                }
+                if let Some(ref consignment) = prepared_payment.consignment {
                     match consignment_file {
+                        None if summary.is_some() => {}
                         None => {
                             eprint!("{} ", "Consignment:".bright_yellow());
                             println!("{}", consignment);
@@ -412,15 +1426,27 @@ impl Exec for InvoiceCommand {
                         }
                     }
                 }
+                if let Some(summary) = summary {
+                    PaymentResult::with(
+                        &prepared_payment.psbt,
+                        prepared_payment
+                            .consignment
+                            .as_ref()
+                            .map(Consignment::to_string),
+                    )
+                    .output_print(summary, output_opts)
+                    .map_err(report_failure)?;
+                }
                 Ok(())
             }
             InvoiceCommand::Accept { consignment, file } => {
-                let consignment = if file {
-                    unimplemented!()
+                let data = if file {
+                    fs::read(&consignment)?
                 } else {
-                    Consignment::from_str(&consignment)
-                        .expect("bad consignment")
+                    consignment.into_bytes()
                 };
+                let consignment =
+                    util::parse_consignment(&data).map_err(report_failure)?;
 
                 client.invoice_accept(consignment).map(|validation| {
                     match validation.validity() {
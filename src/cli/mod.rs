@@ -17,24 +17,30 @@ mod output;
 pub(self) mod util;
 
 pub use opts::{
-    AddressCommand, AssetCommand, Command, DescriptorOpts, Formatting,
-    InvoiceCommand, Opts, PsbtFormat, WalletCommand, WalletCreateCommand,
-    WalletOpts,
+    AddressCommand, AmountArg, AssetCommand, Command, DescriptorOpts,
+    Formatting, InvoiceCommand, Opts, OutputOpts, PsbtFormat, TimeZone,
+    WalletCommand, WalletCreateCommand, WalletOpts,
+};
+pub use output::{
+    AccountXpub, CoinAllocation, CosignerStatus, OutputFormat, PaymentOutput,
+    PaymentResult, PreciseOperation, PreciseUtxo, VersionInfo,
 };
-pub use output::OutputFormat;
 
 // -----------------------------------------------------------------------------
 
 use citadel::client::Config;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+
+impl TryFrom<Opts> for Config {
+    type Error = String;
 
-impl From<Opts> for Config {
-    fn from(opts: crate::cli::Opts) -> Self {
-        Config {
-            rpc_endpoint: opts.shared.rpc_endpoint.try_into().expect(
-                "The provided socket address must be a valid ZMQ socket",
-            ),
+    fn try_from(opts: crate::cli::Opts) -> Result<Self, Self::Error> {
+        let endpoint = opts.shared.rpc_endpoint;
+        Ok(Config {
+            rpc_endpoint: endpoint.clone().try_into().map_err(|err| {
+                format!("invalid RPC endpoint '{}': {}", endpoint, err)
+            })?,
             verbose: opts.shared.verbose,
-        }
+        })
     }
 }
@@ -15,15 +15,38 @@ use clap::{AppSettings, ArgGroup, Clap, ValueHint};
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use bitcoin::Address;
+use bitcoin::{Address, Txid};
 use citadel::model;
 use invoice::Invoice;
+use slip132::KeyApplication;
 use wallet::descriptors;
 use wallet::hd::PubkeyChain;
 use wallet::hd::UnhardenedIndex;
 
 pub const MYCITADEL_CLI_CONFIG: &'static str = "{data_dir}/mycitadel-cli.toml";
 
+/// Maximal length of the wallet name, in characters
+pub const WALLET_NAME_MAX_LEN: usize = 64;
+
+/// Parses wallet name, trimming whitespaces and ensuring that it is not empty,
+/// fits [`WALLET_NAME_MAX_LEN`] and does not contain control characters
+pub fn parse_wallet_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(s!("wallet name must not be empty"));
+    }
+    if name.chars().count() > WALLET_NAME_MAX_LEN {
+        return Err(format!(
+            "wallet name must not exceed {} characters",
+            WALLET_NAME_MAX_LEN
+        ));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(s!("wallet name must not contain control characters"));
+    }
+    Ok(name.to_owned())
+}
+
 #[derive(
     Clap, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display,
 )]
@@ -53,6 +76,23 @@ pub enum Formatting {
     /// Output data as JSON
     #[display("json")]
     Json,
+
+    /// Output data as JSON formatted for human readability
+    #[display("json-pretty")]
+    JsonPretty,
+}
+
+impl Formatting {
+    /// Detects whether the format is a structured data serialization (YAML,
+    /// JSON), which must always produce valid output on STDOUT, even for
+    /// empty data
+    #[inline]
+    pub fn is_structured(self) -> bool {
+        matches!(
+            self,
+            Formatting::Yaml | Formatting::Json | Formatting::JsonPretty
+        )
+    }
 }
 
 impl FromStr for Formatting {
@@ -66,11 +106,90 @@ impl FromStr for Formatting {
             "csv" => Formatting::Csv,
             "yaml" => Formatting::Yaml,
             "json" => Formatting::Json,
+            "json-pretty" => Formatting::JsonPretty,
             _ => Err("Unknown format name")?,
         })
     }
 }
 
+/// Time zone used for displaying timestamps
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum TimeZone {
+    /// Coordinated Universal Time
+    Utc,
+
+    /// Local time zone of the machine running the command
+    Local,
+
+    /// Fixed offset from UTC, in seconds (positive values are east of UTC)
+    Offset(i32),
+}
+
+impl Default for TimeZone {
+    fn default() -> Self {
+        TimeZone::Utc
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "utc" | "z" => return Ok(TimeZone::Utc),
+            "local" => return Ok(TimeZone::Local),
+            _ => {}
+        }
+        let err = || {
+            format!(
+                "invalid time zone `{}`; use `utc`, `local` or UTC offset \
+                 like `+02:00`",
+                s
+            )
+        };
+        let (sign, offset) = if let Some(offset) = s.strip_prefix('+') {
+            (1, offset)
+        } else if let Some(offset) = s.strip_prefix('-') {
+            (-1, offset)
+        } else {
+            return Err(err());
+        };
+        if !offset.chars().all(|c| c.is_ascii_digit() || c == ':') {
+            return Err(err());
+        }
+        // Offsets are accepted as `HH:MM`, `HHMM` or `HH`
+        let (hours, minutes) = match offset.split_once(':') {
+            Some(split) => split,
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| err())?;
+        let minutes: i32 = minutes.parse().map_err(|_| err())?;
+        if hours > 23 || minutes > 59 {
+            return Err(err());
+        }
+        Ok(TimeZone::Offset(sign * (hours * 3600 + minutes * 60)))
+    }
+}
+
+/// Options controlling data output, shared by all commands
+#[derive(Clap, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct OutputOpts {
+    /// Comma-separated list of columns to print for `tab` and `csv` output
+    /// formats, in the given order. Column names are case-insensitive and
+    /// use dashes instead of spaces, like `id,name` or `block-height`.
+    #[clap(long, global = true, use_delimiter = true)]
+    pub columns: Option<Vec<String>>,
+
+    /// Time zone for displaying timestamps in `tab` and `csv` output: `utc`,
+    /// `local` or a fixed UTC offset like `+02:00`. These formats print
+    /// timestamps in ISO-8601 form with explicit offset; structured formats
+    /// (YAML, JSON) keep timestamps in UTC.
+    #[clap(long, global = true, default_value = "utc")]
+    pub timezone: TimeZone,
+}
+
 #[derive(
     Clap, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display,
 )]
@@ -101,6 +220,117 @@ impl FromStr for PsbtFormat {
     }
 }
 
+/// Amount provided in human-readable form with an optional unit, like
+/// `1.5btc`, `100000sat` or `10usdt`. Amounts without unit are treated as
+/// being expressed in atomic (smallest) asset units, so they must not contain
+/// a decimal point.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct AmountArg {
+    integer: u64,
+    fraction: String,
+    unit: Option<String>,
+}
+
+impl AmountArg {
+    /// Converts the amount into atomic asset units using the asset decimal
+    /// precision. Fails if the unit does not match the asset ticker or if the
+    /// amount has more decimal digits than allowed by the asset precision.
+    pub fn atomic_value(
+        &self,
+        precision: u8,
+        ticker: &str,
+    ) -> Result<u64, String> {
+        let precision = match self.unit.as_deref() {
+            None => 0,
+            Some(unit)
+                if ticker == "BTC"
+                    && (unit == "sat"
+                        || unit == "sats"
+                        || unit == "satoshi") =>
+            {
+                0
+            }
+            Some(unit) if unit == ticker.to_lowercase() => precision,
+            Some(unit) => {
+                return Err(format!(
+                    "amount unit `{}` does not match asset ticker `{}`",
+                    unit, ticker
+                ))
+            }
+        };
+        if self.fraction.len() > precision as usize {
+            return Err(format!(
+                "amount has {} decimal digits, while {} allows only {}",
+                self.fraction.len(),
+                ticker,
+                precision
+            ));
+        }
+        let multiplier = 10u64
+            .checked_pow(precision as u32)
+            .ok_or_else(|| s!("asset precision is too large"))?;
+        let fraction =
+            format!("{:0<width$}", self.fraction, width = precision as usize);
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            fraction.parse::<u64>().map_err(|err| err.to_string())?
+        };
+        self.integer
+            .checked_mul(multiplier)
+            .and_then(|value| value.checked_add(fraction))
+            .ok_or_else(|| s!("amount value is too large"))
+    }
+}
+
+impl FromStr for AmountArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let unit = unit.trim();
+        let unit = if unit.is_empty() {
+            None
+        } else {
+            Some(unit.to_owned())
+        };
+
+        let (integer, fraction) = match number.split_once('.') {
+            Some((_, fraction)) if unit.is_none() => {
+                return Err(format!(
+                    "ambiguous amount `{}`: decimal amounts require explicit \
+                     unit, like `{}btc`",
+                    s, s
+                ))
+            }
+            Some((integer, fraction)) => (integer, fraction),
+            None => (number, ""),
+        };
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(format!("amount `{}` does not contain a number", s));
+        }
+        if fraction.contains('.') {
+            return Err(format!("invalid amount `{}`", s));
+        }
+        let integer = if integer.is_empty() {
+            0
+        } else {
+            integer
+                .parse()
+                .map_err(|_| format!("invalid amount `{}`", s))?
+        };
+        Ok(AmountArg {
+            integer,
+            fraction: fraction.trim_end_matches('0').to_owned(),
+            unit,
+        })
+    }
+}
+
 #[derive(Clap, Clone, Debug)]
 #[clap(
     name = "mycitadel-cli",
@@ -129,6 +359,10 @@ pub struct Opts {
     )]
     pub config: String,
 
+    /// Output formatting options
+    #[clap(flatten)]
+    pub output: OutputOpts,
+
     /// Command to execute
     #[clap(subcommand)]
     pub command: Command,
@@ -164,6 +398,14 @@ pub enum Command {
         #[clap(subcommand)]
         subcommand: InvoiceCommand,
     },
+
+    /// Prints version and build information for bug reports
+    #[display("version")]
+    Version {
+        /// How the version information should be formatted
+        #[clap(short, long, default_value = "yaml")]
+        format: Formatting,
+    },
 }
 
 #[derive(Clap, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
@@ -192,7 +434,7 @@ pub enum WalletCommand {
         wallet_id: model::ContractId,
 
         /// New name of the wallet
-        #[clap()]
+        #[clap(parse(try_from_str = parse_wallet_name))]
         new_name: String,
     },
 
@@ -208,6 +450,68 @@ pub enum WalletCommand {
     Balance {
         #[clap(flatten)]
         scan_opts: WalletOpts,
+
+        /// Show only unspent outputs holding the given asset
+        #[clap(short, long)]
+        asset: Option<rgb::ContractId>,
+    },
+
+    /// Computes total value of all wallets in a reference asset using
+    /// provided exchange rates
+    Total {
+        /// YAML file mapping asset ids to the price of a single asset unit
+        /// expressed in the reference asset. Bitcoin may be given by its
+        /// `BTC` ticker, like `BTC: 1.0`; other assets require contract id,
+        /// since tickers are not unique
+        #[clap(long, value_hint = ValueHint::FilePath)]
+        rates: PathBuf,
+
+        /// Whether to re-scan addresses space of each wallet with Electrum
+        /// server
+        #[clap(short, long)]
+        rescan: bool,
+    },
+
+    /// Lists unspent outputs of the wallet together with all assets
+    /// allocated to each of them
+    Coins {
+        #[clap(flatten)]
+        scan_opts: WalletOpts,
+    },
+
+    /// Prints account-level extended public keys of the wallet, which may be
+    /// used for watch-only monitoring or address generation by third-party
+    /// software. Multisig wallets will list extended keys of all cosigners.
+    #[display("xpub {wallet_id}")]
+    Xpub {
+        /// Wallet id to export extended public keys from
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Encode extended public keys with SLIP-132 version bytes matching
+        /// given key application (`bip44`, `bip84`, `bip49`, `bip48-native`,
+        /// `bip48-nested`); defaults to plain BIP32 `xpub`/`tpub` encoding
+        #[clap(short, long)]
+        slip132: Option<KeyApplication>,
+
+        /// How the extended public key list should be formatted
+        #[clap(short, long, default_value = "tab", global = true)]
+        format: Formatting,
+    },
+
+    /// Prints wallet output descriptors in the BIP-380 form, as used by other
+    /// wallet software (like `importdescriptors` command of Bitcoin Core).
+    /// Wallets with `0-1` derivation branch range are printed as a pair of
+    /// receiving and change descriptors.
+    #[display("descriptor {wallet_id}")]
+    Descriptor {
+        /// Wallet id to print descriptor for
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Do not append descriptor checksum
+        #[clap(long = "no-checksum", parse(from_flag = std::ops::Not::not))]
+        checksum: bool,
     },
 
     /// Signs given PSBT with keys controlled by a wallet master extended keys.
@@ -218,7 +522,7 @@ pub enum WalletCommand {
         /// Wallet id to sign PSBT from
         wallet_id: model::ContractId,
 
-        /// PSBT data in Bech32 encoding
+        /// PSBT data in Base64 or hex encoding
         psbt: String,
     },
 
@@ -228,9 +532,92 @@ pub enum WalletCommand {
         /// Wallet id to sign PSBT from
         wallet_id: model::ContractId,
 
-        /// PSBT data in Bech32 encoding
+        /// PSBT data in Base64 or hex encoding
         psbt: String,
     },
+
+    /// Lists operations from the wallet history, optionally filtered by the
+    /// payment direction and asset
+    #[display("history {wallet_id}")]
+    History {
+        /// Wallet id to list operations from
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// List only incoming payments
+        #[clap(long, conflicts_with = "outgoing")]
+        incoming: bool,
+
+        /// List only outgoing payments
+        #[clap(long)]
+        outgoing: bool,
+
+        /// List only operations transferring the given asset. Bitcoin
+        /// transfers are listed when the bitcoin asset id (matching genesis
+        /// block hash) is given
+        #[clap(short, long)]
+        asset: Option<rgb::ContractId>,
+
+        /// How the operation list should be formatted
+        #[clap(short, long, default_value = "tab")]
+        format: Formatting,
+    },
+
+    /// Prints PSBT stored for a wallet operation, which allows to resume
+    /// signing of transactions which were composed but not yet published
+    #[display("get-psbt {wallet_id} {txid}")]
+    GetPsbt {
+        /// Wallet id containing the operation
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Id of the transaction performing the operation
+        #[clap()]
+        txid: Txid,
+
+        /// File name to output PSBT. If no name is given PSBT data are output
+        /// to STDOUT
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        /// PSBT format to use for the output; if no file is specified defaults
+        /// to Base64 output; otherwise defaults to binary
+        #[clap(short, long)]
+        format: Option<PsbtFormat>,
+    },
+
+    /// Prints which of the wallet cosigners have signed PSBT stored for a
+    /// wallet operation
+    #[display("signing-status {wallet_id} {txid}")]
+    SigningStatus {
+        /// Wallet id containing the operation
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Id of the transaction performing the operation
+        #[clap()]
+        txid: Txid,
+
+        /// How the cosigner list should be formatted
+        #[clap(short, long, default_value = "tab")]
+        format: Formatting,
+    },
+
+    /// Prints details of a single wallet operation
+    #[display("operation {wallet_id} {txid}")]
+    Operation {
+        /// Wallet id containing the operation
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Id of the transaction performing the operation
+        #[clap()]
+        txid: Txid,
+
+        /// How the operation details should be formatted
+        #[clap(short, long, default_value = "yaml")]
+        format: Formatting,
+    },
 }
 
 #[derive(Clap, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
@@ -240,7 +627,7 @@ pub enum WalletCreateCommand {
     #[display("single-sig {name} {pubkey_chain}")]
     SingleSig {
         /// Wallet name
-        #[clap()]
+        #[clap(parse(try_from_str = parse_wallet_name))]
         name: String,
 
         /// Extended public key with derivation info.
@@ -261,9 +648,46 @@ pub enum WalletCreateCommand {
         #[clap()]
         pubkey_chain: PubkeyChain,
 
+        /// Validates that the wallet can be used with a hardware signing
+        /// device. Nothing is stored with the wallet; the flag only checks
+        /// before creation that public key generator contains key origin
+        /// with the device master key fingerprint (like
+        /// `m=[6734cda8]/84'/0'/0'=...`), which is needed for matching PSBT
+        /// inputs to the device at signing time.
+        #[clap(long)]
+        hardware: bool,
+
         #[clap(flatten)]
         opts: DescriptorOpts,
     },
+
+    /// Creates single-sig wallets from output descriptors exported by Bitcoin
+    /// Core (`listdescriptors`) or other wallet software. A separate wallet is
+    /// created for each script type and account key.
+    #[display("import-descriptor {name}")]
+    ImportDescriptor {
+        /// Wallet name. When descriptors contain more than a single account
+        /// key, each wallet name is suffixed with its script type.
+        #[clap(parse(try_from_str = parse_wallet_name))]
+        name: String,
+
+        /// Single-key output descriptors, like
+        /// `wpkh([6734cda8/84h/0h/0h]xpub.../0/*)#checksum`. Receiving and
+        /// change descriptors for the same account key are combined into a
+        /// single wallet. Taproot descriptors are not supported.
+        #[clap(required_unless_present = "file")]
+        descriptors: Vec<String>,
+
+        /// JSON file with descriptors, containing either `importdescriptors`
+        /// request array or `listdescriptors` command output
+        #[clap(
+            short,
+            long,
+            conflicts_with = "descriptors",
+            value_hint = ValueHint::FilePath
+        )]
+        file: Option<PathBuf>,
+    },
 }
 
 #[derive(Clap, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -298,6 +722,95 @@ pub enum AddressCommand {
         format: Formatting,
     },
 
+    /// Generate a batch of consecutive unused addresses, starting from the
+    /// next unused derivation index
+    Batch {
+        /// Wallet for address generation
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Number of addresses to generate
+        #[clap(short, long, default_value = "10")]
+        count: u16,
+
+        /// Whether to mark addresses as used
+        #[clap(short = 'u', long = "unmark", parse(from_flag = std::ops::Not::not))]
+        mark_used: bool,
+
+        /// Use SegWit legacy address format (applicable only to a SegWit
+        /// wallets)
+        #[clap(long, takes_value = false)]
+        legacy: bool,
+
+        /// How the address list output should be formatted
+        #[clap(short, long, default_value = "tab")]
+        format: Formatting,
+    },
+
+    /// Derive addresses for a range of derivation indexes without marking
+    /// them as used. Useful for comparing derivation with other wallets
+    /// software.
+    Derive {
+        /// Wallet to derive addresses for
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// First derivation index of the range
+        #[clap(long, default_value = "0")]
+        from: u16,
+
+        /// Last derivation index of the range (inclusive)
+        #[clap(long, default_value = "19")]
+        to: u16,
+
+        /// Use SegWit legacy address format (applicable only to a SegWit
+        /// wallets)
+        #[clap(long, takes_value = false)]
+        legacy: bool,
+
+        /// How the address list output should be formatted
+        #[clap(short, long, default_value = "tab")]
+        format: Formatting,
+    },
+
+    /// Mark addresses already used by other wallet software as used, so they
+    /// will not be given out again. Each address is checked to match the
+    /// wallet derivation at the given index before anything is marked.
+    ImportUsed {
+        /// Wallet to import used addresses into
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Text file with a derivation index and an address on each line,
+        /// separated with whitespace or comma. Empty lines and lines starting
+        /// with `#` are ignored.
+        #[clap(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+
+        /// Addresses use SegWit legacy format (applicable only to a SegWit
+        /// wallets)
+        #[clap(long, takes_value = false)]
+        legacy: bool,
+    },
+
+    /// Request testnet or signet coins from a faucet to a new wallet address.
+    /// Does nothing for mainnet wallets.
+    Faucet {
+        /// Wallet to receive the coins
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Faucet URL; the newly generated address is sent to it with HTTP
+        /// POST request as an `address` form field
+        #[clap(long, env = "MYCITADEL_FAUCET_URL")]
+        faucet_url: String,
+
+        /// Use SegWit legacy address format (applicable only to a SegWit
+        /// wallets)
+        #[clap(long, takes_value = false)]
+        legacy: bool,
+    },
+
     MarkUsed {
         /// Wallet for using the address
         #[clap()]
@@ -317,12 +830,36 @@ pub enum AddressCommand {
         unmark: bool,
     },
 
+    /// Verify that the address belongs to the wallet, i.e. is derived from
+    /// the wallet descriptor, and print its derivation index
+    Verify {
+        /// Wallet which should contain the address
+        #[clap()]
+        wallet_id: model::ContractId,
+
+        /// Address to verify
+        #[clap()]
+        address: Address,
+
+        /// How many addresses should be checked after the last used address
+        /// before giving up. Defaults to 20
+        #[clap(long)]
+        lookup_depth: Option<u8>,
+
+        /// Use SegWit legacy address format (applicable only to a SegWit
+        /// wallets)
+        #[clap(long, takes_value = false)]
+        legacy: bool,
+    },
+
     Pay {
         /// Address to pay to
         address: Address,
 
-        /// Amount to pay to the address
-        amount: bitcoin::Amount,
+        /// Amount to pay to the address, with an explicit unit (`btc` or
+        /// `sat`), like `0.001btc`; amounts without unit are treated as
+        /// satoshis
+        amount: AmountArg,
 
         /// Fee to pay, in satoshis
         fee: u64,
@@ -339,6 +876,11 @@ pub enum AddressCommand {
         /// to Base64 output; otherwise defaults to binary
         #[clap(short, long)]
         format: Option<PsbtFormat>,
+
+        /// Print payment summary (transaction id, inputs, outputs, fee and
+        /// Base64-encoded PSBT) in the given format instead of the bare PSBT
+        #[clap(long)]
+        summary: Option<Formatting>,
     },
 }
 
@@ -353,13 +895,31 @@ pub enum AssetCommand {
         format: Formatting,
     },
 
+    /// Print detailed information about a single asset
+    #[display("info {asset_id}")]
+    Info {
+        /// Asset id to print information about
+        #[clap()]
+        asset_id: rgb::ContractId,
+
+        /// Format to use for the asset information
+        #[clap(short, long, default_value = "yaml", global = true)]
+        format: Formatting,
+    },
+
     /// Import asset genesis data
     #[display("import")]
     Import {
         /// Bech32-representation of the asset genesis (string starting with
         /// `genesis1....`
-        #[clap()]
-        genesis: String,
+        #[clap(required_unless_present = "file")]
+        genesis: Option<String>,
+
+        /// Import asset from a consignment file instead of the genesis string.
+        /// The file may contain Bech32 or hex-encoded consignment string or
+        /// raw strict-encoded binary data.
+        #[clap(short, long, conflicts_with = "genesis")]
+        file: Option<PathBuf>,
     },
 }
 
@@ -447,12 +1007,15 @@ pub enum InvoiceCommand {
         #[clap()]
         fee: u64,
 
-        /// Force payment with the specified amount (always in satoshis).
-        /// Required for invoices that does not provide amount field.
-        /// For other types of invoices, if provided, overrides the
-        /// amount found in the invoice.
+        /// Force payment with the specified amount. Required for invoices
+        /// that does not provide amount field. For other types of invoices,
+        /// if provided, overrides the amount found in the invoice.
+        ///
+        /// The amount may be given with a unit matching the invoice asset,
+        /// like `1.5btc`, `100000sat` or `10usdt`; amounts without unit are
+        /// treated as atomic asset units (satoshis for bitcoin).
         #[clap(short, long)]
-        amount: Option<u64>,
+        amount: Option<AmountArg>,
 
         /// File name to output PSBT. If no name is given PSBT data are output
         /// to STDOUT
@@ -473,6 +1036,12 @@ pub enum InvoiceCommand {
         /// allowed only when paying descriptor-based RGB invoices
         #[clap(short, long)]
         giveaway: Option<u64>,
+
+        /// Print payment summary (transaction id, inputs, outputs, fee,
+        /// Base64-encoded PSBT and Bech32 consignment) in the given format
+        /// instead of the bare PSBT and consignment data
+        #[clap(long)]
+        summary: Option<Formatting>,
     },
 
     /// Accept payment for the invoice. Required only for on-chain RGB
@@ -480,12 +1049,13 @@ pub enum InvoiceCommand {
     /// lightning) are accepted automatically and does not require calling
     /// this method.
     Accept {
-        /// Consignment data to accept
+        /// Consignment data to accept, as Bech32 or hex string
         #[clap()]
         consignment: String,
 
-        /// Whether parameter given by consignment is a file name or a Bech32
-        /// string
+        /// Whether parameter given by consignment is a file name. The file
+        /// may contain consignment in Bech32, hex or binary strict-encoded
+        /// form.
         #[clap(short, long)]
         file: bool,
     },
@@ -548,3 +1118,116 @@ impl DescriptorOpts {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wallet_name_trimmed() {
+        assert_eq!(parse_wallet_name("  Savings "), Ok(s!("Savings")));
+        assert_eq!(parse_wallet_name("Мой кошелёк"), Ok(s!("Мой кошелёк")));
+    }
+
+    #[test]
+    fn wallet_name_empty() {
+        assert!(parse_wallet_name("").is_err());
+        assert!(parse_wallet_name(" \t ").is_err());
+    }
+
+    #[test]
+    fn wallet_name_length() {
+        let name = "ё".repeat(WALLET_NAME_MAX_LEN);
+        assert_eq!(parse_wallet_name(&name), Ok(name.clone()));
+        assert!(parse_wallet_name(&format!("{}x", name)).is_err());
+    }
+
+    #[test]
+    fn wallet_name_control_chars() {
+        assert!(parse_wallet_name("my\nwallet").is_err());
+        assert!(parse_wallet_name("my\u{1b}[31mwallet").is_err());
+    }
+
+    #[test]
+    fn amount_with_unit() {
+        let amount = AmountArg::from_str("1.5BTC").unwrap();
+        assert_eq!(amount.atomic_value(8, "BTC"), Ok(150_000_000));
+        let amount = AmountArg::from_str(".5btc").unwrap();
+        assert_eq!(amount.atomic_value(8, "BTC"), Ok(50_000_000));
+        let amount = AmountArg::from_str("1000 sat").unwrap();
+        assert_eq!(amount.atomic_value(8, "BTC"), Ok(1000));
+        let amount = AmountArg::from_str("10usdt").unwrap();
+        assert_eq!(amount.atomic_value(2, "USDT"), Ok(1000));
+    }
+
+    #[test]
+    fn amount_without_unit() {
+        let amount = AmountArg::from_str("100000").unwrap();
+        assert_eq!(amount.atomic_value(8, "BTC"), Ok(100_000));
+        assert!(AmountArg::from_str("1.5").is_err());
+    }
+
+    #[test]
+    fn amount_malformed() {
+        assert!(AmountArg::from_str("1.5.3btc").is_err());
+        assert!(AmountArg::from_str(".btc").is_err());
+        assert!(AmountArg::from_str("btc").is_err());
+        assert!(AmountArg::from_str("").is_err());
+    }
+
+    #[test]
+    fn amount_wrong_ticker() {
+        let amount = AmountArg::from_str("10usdt").unwrap();
+        assert!(amount.atomic_value(8, "BTC").is_err());
+        let amount = AmountArg::from_str("10sat").unwrap();
+        assert!(amount.atomic_value(2, "USDT").is_err());
+    }
+
+    #[test]
+    fn amount_precision() {
+        let amount = AmountArg::from_str("0.123456789btc").unwrap();
+        assert!(amount.atomic_value(8, "BTC").is_err());
+        let amount = AmountArg::from_str("0.12345678btc").unwrap();
+        assert_eq!(amount.atomic_value(8, "BTC"), Ok(12_345_678));
+    }
+
+    #[test]
+    fn amount_overflow() {
+        assert!(AmountArg::from_str("99999999999999999999").is_err());
+        let amount = AmountArg::from_str("200000000000btc").unwrap();
+        assert!(amount.atomic_value(8, "BTC").is_err());
+        let amount = AmountArg::from_str("18446744073709551615").unwrap();
+        assert_eq!(amount.atomic_value(8, "BTC"), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn timezone_names() {
+        assert_eq!(TimeZone::from_str("UTC"), Ok(TimeZone::Utc));
+        assert_eq!(TimeZone::from_str("z"), Ok(TimeZone::Utc));
+        assert_eq!(TimeZone::from_str(" local "), Ok(TimeZone::Local));
+    }
+
+    #[test]
+    fn timezone_offsets() {
+        assert_eq!(
+            TimeZone::from_str("+05:30"),
+            Ok(TimeZone::Offset(5 * 3600 + 30 * 60))
+        );
+        assert_eq!(
+            TimeZone::from_str("-0800"),
+            Ok(TimeZone::Offset(-8 * 3600))
+        );
+        assert_eq!(TimeZone::from_str("+2"), Ok(TimeZone::Offset(2 * 3600)));
+    }
+
+    #[test]
+    fn timezone_invalid() {
+        assert!(TimeZone::from_str("").is_err());
+        assert!(TimeZone::from_str("é").is_err());
+        assert!(TimeZone::from_str("+é").is_err());
+        assert!(TimeZone::from_str("05:30").is_err());
+        assert!(TimeZone::from_str("+24:00").is_err());
+        assert!(TimeZone::from_str("+05:60").is_err());
+        assert!(TimeZone::from_str("++5").is_err());
+    }
+}
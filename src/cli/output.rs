@@ -11,45 +11,193 @@
 // along with this software.
 // If not, see <https://www.gnu.org/licenses/agpl-3.0-standalone.html>.
 
-use chrono::NaiveDateTime;
+use chrono::{
+    DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat, Utc,
+};
 use colored::Colorize;
 use serde::Serialize;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 
 use amplify::Wrapper;
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::hex::ToHex;
 use bitcoin::hashes::{sha256t, Hash};
+use bitcoin::util::bip32::Fingerprint;
+use bitcoin::{OutPoint, Script, Txid};
 use invoice::Invoice;
 use wallet::blockchain::BITCOIN_GENESIS_BLOCKHASH;
-use wallet::hd::UnhardenedIndex;
+use wallet::hd::{PubkeyChain, UnhardenedIndex};
+use wallet::psbt::Psbt;
 
-use citadel::model::{AddressDerivation, ContractMeta, Utxo};
+use citadel::model::{
+    AddressDerivation, ContractMeta, Operation, PaymentDirecton, Utxo,
+};
 
-use super::Formatting;
+use super::util;
+use super::{Formatting, OutputOpts, TimeZone};
+
+/// Formats timestamp, which is always kept in UTC, as ISO-8601 string in the
+/// time zone requested with `--timezone` option
+fn format_datetime(datetime: &NaiveDateTime, opts: &OutputOpts) -> String {
+    let datetime = DateTime::<Utc>::from_utc(*datetime, Utc);
+    match opts.timezone {
+        TimeZone::Utc => datetime.to_rfc3339_opts(SecondsFormat::Secs, true),
+        TimeZone::Local => datetime
+            .with_timezone(&Local)
+            .to_rfc3339_opts(SecondsFormat::Secs, false),
+        TimeZone::Offset(offset) => datetime
+            .with_timezone(&FixedOffset::east(offset))
+            .to_rfc3339_opts(SecondsFormat::Secs, false),
+    }
+}
+
+/// Normalizes column name, such that it can be matched against user input
+fn column_key(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "-")
+}
+
+/// Selects header-value pairs for the columns requested with `--columns`
+/// option, in the requested order. Fails if some of the requested columns are
+/// not present in the record, listing all valid column names.
+fn select_columns(
+    record: Vec<(String, String)>,
+    opts: &OutputOpts,
+) -> Result<Vec<(String, String)>, String> {
+    let columns = match opts.columns.as_ref() {
+        None => return Ok(record),
+        Some(columns) => columns,
+    };
+    columns
+        .iter()
+        .map(|name| {
+            let key = column_key(name);
+            record
+                .iter()
+                .find(|(header, _)| column_key(header) == key)
+                .cloned()
+                .ok_or_else(|| {
+                    format!(
+                        "unknown column `{}`; valid columns are: {}",
+                        name,
+                        record
+                            .iter()
+                            .map(|(header, _)| column_key(header))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Checks that all columns requested with `--columns` option are present in
+/// the output of type `T`, such that the command can fail before doing any
+/// work
+pub(super) fn check_columns<T: OutputFormat>(
+    opts: &OutputOpts,
+) -> Result<(), String> {
+    let record = T::output_headers().into_iter().map(|h| (h.clone(), h));
+    select_columns(record.collect(), opts).map(|_| ())
+}
+
+/// Prints column headers for tab- and comma-separated output formats
+fn print_headers(
+    headers: Vec<String>,
+    format: Formatting,
+    opts: &OutputOpts,
+) -> Result<(), String> {
+    let separator = match format {
+        Formatting::Tab => "\t",
+        Formatting::Csv => ",",
+        _ => return Ok(()),
+    };
+    let record = headers.into_iter().map(|h| (h.clone(), h)).collect();
+    let headers = select_columns(record, opts)?
+        .into_iter()
+        .map(|(header, _)| header)
+        .collect::<Vec<_>>()
+        .join(separator);
+    if format == Formatting::Tab {
+        println!("{}", headers.bright_green())
+    } else {
+        println!("{}", headers)
+    }
+    Ok(())
+}
+
+/// Prints values of the record columns requested with `--columns` option
+fn print_record(
+    record: Vec<(String, String)>,
+    separator: &str,
+    opts: &OutputOpts,
+) -> Result<(), String> {
+    println!(
+        "{}",
+        select_columns(record, opts)?
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>()
+            .join(separator)
+    );
+    Ok(())
+}
 
 pub trait OutputCompact {
     fn output_compact(&self) -> String;
 }
 
 pub trait OutputFormat: OutputCompact + Serialize {
-    fn output_print(&self, format: Formatting) {
+    fn output_print(
+        &self,
+        format: Formatting,
+        opts: &OutputOpts,
+    ) -> Result<(), String> {
         match format {
             Formatting::Id => println!("{}", self.output_id_string()),
             Formatting::Compact => println!("{}", self.output_compact()),
-            Formatting::Tab => println!("{}", self.output_fields().join("\t")),
-            Formatting::Csv => println!("{}", self.output_fields().join(",")),
+            Formatting::Tab => {
+                print_record(self.output_record(opts)?, "\t", opts)?
+            }
+            Formatting::Csv => {
+                print_record(self.output_record(opts)?, ",", opts)?
+            }
             Formatting::Yaml => {
                 println!("{}", serde_yaml::to_string(self).unwrap_or_default())
             }
             Formatting::Json => {
                 println!("{}", serde_json::to_string(self).unwrap_or_default())
             }
+            Formatting::JsonPretty => println!(
+                "{}",
+                serde_json::to_string_pretty(self).unwrap_or_default()
+            ),
         }
+        Ok(())
     }
 
     fn output_headers() -> Vec<String>;
     fn output_id_string(&self) -> String;
-    fn output_fields(&self) -> Vec<String>;
+    fn output_fields(&self, opts: &OutputOpts) -> Vec<String>;
+
+    /// Returns output fields together with their column headers. Fails if
+    /// the number of fields does not match the number of headers.
+    fn output_record(
+        &self,
+        opts: &OutputOpts,
+    ) -> Result<Vec<(String, String)>, String> {
+        let headers = Self::output_headers();
+        let fields = self.output_fields(opts);
+        if headers.len() != fields.len() {
+            return Err(format!(
+                "internal error: output has {} columns ({}), but {} values",
+                headers.len(),
+                headers.join(", "),
+                fields.len()
+            ));
+        }
+        Ok(headers.into_iter().zip(fields).collect())
+    }
 }
 
 #[doc(hidden)]
@@ -66,18 +214,34 @@ impl<T> OutputFormat for Vec<T>
 where
     T: OutputFormat,
 {
-    fn output_print(&self, format: Formatting) {
-        if self.is_empty() {
+    fn output_print(
+        &self,
+        format: Formatting,
+        opts: &OutputOpts,
+    ) -> Result<(), String> {
+        if self.is_empty() && !format.is_structured() {
             eprintln!("{}", "No items".red());
-            return;
+            return Ok(());
         }
-        let headers = T::output_headers();
-        if format == Formatting::Tab {
-            println!("{}", headers.join("\t").bright_green())
-        } else if format == Formatting::Csv {
-            println!("{}", headers.join(","))
+        print_headers(T::output_headers(), format, opts)?;
+
+        match format {
+            Formatting::Yaml => {
+                println!("{}", serde_yaml::to_string(self).unwrap_or_default())
+            }
+
+            Formatting::Json => {
+                println!("{}", serde_json::to_string(self).unwrap_or_default())
+            }
+
+            Formatting::JsonPretty => println!(
+                "{}",
+                serde_json::to_string_pretty(self).unwrap_or_default()
+            ),
+
+            _ => self.iter().try_for_each(|t| t.output_print(format, opts))?,
         }
-        self.iter().for_each(|t| t.output_print(format));
+        Ok(())
     }
 
     #[doc(hidden)]
@@ -85,13 +249,12 @@ where
         unreachable!()
     }
 
-    #[doc(hidden)]
     fn output_headers() -> Vec<String> {
-        unreachable!()
+        T::output_headers()
     }
 
     #[doc(hidden)]
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
         unreachable!()
     }
 }
@@ -111,17 +274,16 @@ where
     K: Clone + Display + std::hash::Hash + Eq + Serialize,
     V: OutputFormat + Serialize,
 {
-    fn output_print(&self, format: Formatting) {
-        if self.is_empty() {
+    fn output_print(
+        &self,
+        format: Formatting,
+        opts: &OutputOpts,
+    ) -> Result<(), String> {
+        if self.is_empty() && !format.is_structured() {
             eprintln!("{}", "No items".red());
-            return;
-        }
-        let headers = Self::output_headers();
-        if format == Formatting::Tab {
-            println!("{}", headers.join("\t").bright_green())
-        } else if format == Formatting::Csv {
-            println!("{}", headers.join(","))
+            return Ok(());
         }
+        print_headers(Self::output_headers(), format, opts)?;
 
         match format {
             Formatting::Yaml => {
@@ -132,20 +294,30 @@ where
                 println!("{}", serde_json::to_string(self).unwrap_or_default())
             }
 
-            _ => self.iter().for_each(|(id, rec)| match format {
-                Formatting::Id => println!("{}", id),
-                Formatting::Compact => {
-                    println!("{}#{}", rec.output_compact(), id)
-                }
-                Formatting::Tab => {
-                    println!("{}\t{}", id, rec.output_fields().join("\t"))
-                }
-                Formatting::Csv => {
-                    println!("{},{}", id, rec.output_fields().join(","))
+            Formatting::JsonPretty => println!(
+                "{}",
+                serde_json::to_string_pretty(self).unwrap_or_default()
+            ),
+
+            _ => self.iter().try_for_each(|(id, rec)| {
+                match format {
+                    Formatting::Id => println!("{}", id),
+                    Formatting::Compact => {
+                        println!("{}#{}", rec.output_compact(), id)
+                    }
+                    Formatting::Tab | Formatting::Csv => {
+                        let mut record = vec![(s!("ID"), id.to_string())];
+                        record.extend(rec.output_record(opts)?);
+                        let separator =
+                            if format == Formatting::Tab { "\t" } else { "," };
+                        print_record(record, separator, opts)?
+                    }
+                    _ => unreachable!(),
                 }
-                _ => unreachable!(),
-            }),
+                Ok::<_, String>(())
+            })?,
         }
+        Ok(())
     }
 
     fn output_headers() -> Vec<String> {
@@ -160,7 +332,7 @@ where
     }
 
     #[doc(hidden)]
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
         unreachable!()
     }
 }
@@ -180,17 +352,16 @@ where
     K: Clone + Display + Ord + Serialize,
     V: OutputFormat + Ord + Serialize,
 {
-    fn output_print(&self, format: Formatting) {
-        if self.values().all(Vec::is_empty) {
+    fn output_print(
+        &self,
+        format: Formatting,
+        opts: &OutputOpts,
+    ) -> Result<(), String> {
+        if self.values().all(Vec::is_empty) && !format.is_structured() {
             eprintln!("{}", "No items".red());
-            return;
-        }
-        let headers = Self::output_headers();
-        if format == Formatting::Tab {
-            println!("{}", headers.join("\t").bright_green())
-        } else if format == Formatting::Csv {
-            println!("{}", headers.join(","))
+            return Ok(());
         }
+        print_headers(Self::output_headers(), format, opts)?;
 
         match format {
             Formatting::Yaml => {
@@ -201,23 +372,36 @@ where
                 println!("{}", serde_json::to_string(self).unwrap_or_default())
             }
 
-            _ => self.iter().for_each(|(id, details)| {
-                let id = id.to_string().as_str().bright_white();
-                details.iter().for_each(|rec| match format {
-                    Formatting::Id => println!("{}", id),
-                    Formatting::Compact => {
-                        println!("{}#{}", rec.output_compact(), id)
-                    }
-                    Formatting::Tab => {
-                        println!("{}\t{}", id, rec.output_fields().join("\t"))
-                    }
-                    Formatting::Csv => {
-                        println!("{},{}", id, rec.output_fields().join(","))
+            Formatting::JsonPretty => println!(
+                "{}",
+                serde_json::to_string_pretty(self).unwrap_or_default()
+            ),
+
+            _ => self.iter().try_for_each(|(id, details)| {
+                let id = id.to_string().as_str().bright_white().to_string();
+                details.iter().try_for_each(|rec| {
+                    match format {
+                        Formatting::Id => println!("{}", id),
+                        Formatting::Compact => {
+                            println!("{}#{}", rec.output_compact(), id)
+                        }
+                        Formatting::Tab | Formatting::Csv => {
+                            let mut record = vec![(s!("ID"), id.clone())];
+                            record.extend(rec.output_record(opts)?);
+                            let separator = if format == Formatting::Tab {
+                                "\t"
+                            } else {
+                                ","
+                            };
+                            print_record(record, separator, opts)?
+                        }
+                        _ => unreachable!(),
                     }
-                    _ => unreachable!(),
+                    Ok::<_, String>(())
                 })
-            }),
+            })?,
         }
+        Ok(())
     }
 
     fn output_headers() -> Vec<String> {
@@ -232,7 +416,7 @@ where
     }
 
     #[doc(hidden)]
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
         unreachable!()
     }
 }
@@ -254,12 +438,339 @@ impl OutputFormat for ContractMeta {
         self.id().to_string()
     }
 
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, opts: &OutputOpts) -> Vec<String> {
         vec![
             self.id().to_string().as_str().bright_white().to_string(),
             self.policy().to_string(),
             self.name().to_owned(),
-            self.created_at().to_string(),
+            format_datetime(&self.created_at(), opts),
+        ]
+    }
+}
+
+// MARK: PaymentResult ---------------------------------------------------------
+
+/// Transaction output created by a payment
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct PaymentOutput {
+    pub script_pubkey: Script,
+    pub value: u64,
+}
+
+/// Summary of the prepared payment, as produced by `pay` commands
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct PaymentResult {
+    pub txid: Txid,
+    pub inputs: Vec<OutPoint>,
+    pub outputs: Vec<PaymentOutput>,
+
+    /// Transaction fee, in satoshis; absent if some of PSBT inputs do not
+    /// provide information on the spent output
+    pub fee: Option<u64>,
+
+    /// Virtual size of the unsigned transaction; signed transaction will be
+    /// larger by the size of witness data
+    pub vsize: usize,
+
+    /// Base64-encoded PSBT
+    pub psbt: String,
+
+    /// Bech32-encoded RGB consignment, present for RGB payments
+    pub consignment: Option<String>,
+}
+
+impl PaymentResult {
+    pub fn with(psbt: &Psbt, consignment: Option<String>) -> PaymentResult {
+        let tx = &psbt.global.unsigned_tx;
+        let input_amount = psbt
+            .inputs
+            .iter()
+            .zip(&tx.input)
+            .map(|(input, txin)| {
+                input
+                    .witness_utxo
+                    .as_ref()
+                    .map(|txout| txout.value)
+                    .or_else(|| {
+                        input.non_witness_utxo.as_ref().and_then(|prev_tx| {
+                            prev_tx
+                                .output
+                                .get(txin.previous_output.vout as usize)
+                                .map(|txout| txout.value)
+                        })
+                    })
+            })
+            .sum::<Option<u64>>();
+        let output_amount =
+            tx.output.iter().map(|txout| txout.value).sum::<u64>();
+        PaymentResult {
+            txid: tx.txid(),
+            inputs: tx.input.iter().map(|txin| txin.previous_output).collect(),
+            outputs: tx
+                .output
+                .iter()
+                .map(|txout| PaymentOutput {
+                    script_pubkey: txout.script_pubkey.clone(),
+                    value: txout.value,
+                })
+                .collect(),
+            fee: input_amount
+                .and_then(|amount| amount.checked_sub(output_amount)),
+            vsize: (tx.get_weight() + 3) / 4,
+            psbt: base64::encode(serialize(psbt)),
+            consignment,
+        }
+    }
+}
+
+impl OutputCompact for PaymentResult {
+    fn output_compact(&self) -> String {
+        self.txid.to_string()
+    }
+}
+
+impl OutputFormat for PaymentResult {
+    fn output_headers() -> Vec<String> {
+        vec![
+            s!("Txid"),
+            s!("Inputs"),
+            s!("Outputs"),
+            s!("Fee"),
+            s!("Vsize"),
+            s!("PSBT"),
+        ]
+    }
+
+    fn output_id_string(&self) -> String {
+        self.txid.to_string()
+    }
+
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
+        vec![
+            self.txid.to_string().as_str().bright_white().to_string(),
+            self.inputs
+                .iter()
+                .map(OutPoint::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            self.outputs
+                .iter()
+                .map(|output| {
+                    format!(
+                        "{}:{}",
+                        output.script_pubkey.to_hex(),
+                        output.value
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            self.fee
+                .map(|fee| fee.to_string())
+                .unwrap_or_else(|| s!("?")),
+            self.vsize.to_string(),
+            self.psbt.clone(),
+        ]
+    }
+}
+
+// MARK: VersionInfo -----------------------------------------------------------
+
+/// Version and build information of the command-line tool
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct VersionInfo {
+    /// Crate version
+    pub version: String,
+
+    /// Abbreviated hash of the git commit the tool was built from
+    pub git_hash: String,
+
+    /// Optional crate features enabled at compile time
+    pub features: Vec<String>,
+}
+
+impl VersionInfo {
+    /// Returns information on the currently running build
+    pub fn current() -> VersionInfo {
+        let mut features = vec![];
+        if cfg!(feature = "rgb_node") {
+            features.push(s!("rgb_node"));
+        }
+        VersionInfo {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            git_hash: env!("MYCITADEL_GIT_HASH").to_owned(),
+            features,
+        }
+    }
+}
+
+impl OutputCompact for VersionInfo {
+    fn output_compact(&self) -> String {
+        format!("{} ({})", self.version, self.git_hash)
+    }
+}
+
+impl OutputFormat for VersionInfo {
+    fn output_headers() -> Vec<String> {
+        vec![s!("Version"), s!("Git commit"), s!("Features")]
+    }
+
+    fn output_id_string(&self) -> String {
+        self.version.clone()
+    }
+
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
+        vec![
+            self.version.as_str().bright_white().to_string(),
+            self.git_hash.clone(),
+            self.features.join(" "),
+        ]
+    }
+}
+
+// MARK: AccountXpub -----------------------------------------------------------
+
+/// Account-level extended public key of a wallet, as exported by
+/// `wallet xpub` command
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct AccountXpub {
+    /// Fingerprint of the master key the account key is derived from
+    pub fingerprint: String,
+
+    /// Extended public key, either in BIP32 or SLIP-132 encoding
+    pub xpub: String,
+
+    /// Full key chain with the derivation origin information
+    pub pubkey_chain: PubkeyChain,
+}
+
+impl OutputCompact for AccountXpub {
+    fn output_compact(&self) -> String {
+        format!("[{}]{}", self.fingerprint, self.xpub)
+    }
+}
+
+impl OutputFormat for AccountXpub {
+    fn output_headers() -> Vec<String> {
+        vec![
+            s!("Fingerprint"),
+            s!("Extended public key"),
+            s!("Key chain"),
+        ]
+    }
+
+    fn output_id_string(&self) -> String {
+        self.xpub.clone()
+    }
+
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
+        vec![
+            self.fingerprint.as_str().bright_white().to_string(),
+            self.xpub.clone(),
+            self.pubkey_chain.to_string(),
+        ]
+    }
+}
+
+// MARK: CosignerStatus --------------------------------------------------------
+
+/// Signing status of a single wallet cosigner for a stored PSBT
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize)]
+pub struct CosignerStatus {
+    /// Master key fingerprint of the cosigner
+    pub fingerprint: String,
+
+    /// Number of inputs signed by the cosigner
+    pub signed: usize,
+
+    /// Number of inputs which are derived from the cosigner key and require
+    /// its signature
+    pub inputs: usize,
+}
+
+impl CosignerStatus {
+    /// Matches signatures present in the PSBT inputs with the cosigner keys
+    /// by the master key fingerprints from the input key derivation data
+    pub fn with_psbt(
+        psbt: &Psbt,
+        fingerprints: &[Fingerprint],
+    ) -> Vec<CosignerStatus> {
+        fingerprints
+            .iter()
+            .map(|fingerprint| {
+                let (signed, inputs) = psbt
+                    .inputs
+                    .iter()
+                    .filter_map(|input| {
+                        let keys = input
+                            .bip32_derivation
+                            .iter()
+                            .filter(|(_, (master, _))| master == fingerprint)
+                            .map(|(pubkey, _)| pubkey)
+                            .collect::<Vec<_>>();
+                        if keys.is_empty() {
+                            return None;
+                        }
+                        let finalized = input.final_script_sig.is_some()
+                            || input.final_script_witness.is_some();
+                        Some(
+                            finalized
+                                || keys.into_iter().any(|pubkey| {
+                                    input.partial_sigs.contains_key(pubkey)
+                                }),
+                        )
+                    })
+                    .fold((0usize, 0usize), |(signed, inputs), is_signed| {
+                        (signed + is_signed as usize, inputs + 1)
+                    });
+                CosignerStatus {
+                    fingerprint: fingerprint.to_string(),
+                    signed,
+                    inputs,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the cosigner has signed all inputs requiring its signature
+    pub fn is_complete(&self) -> bool {
+        self.inputs > 0 && self.signed == self.inputs
+    }
+}
+
+impl OutputCompact for CosignerStatus {
+    fn output_compact(&self) -> String {
+        format!("[{}]{}/{}", self.fingerprint, self.signed, self.inputs)
+    }
+}
+
+impl OutputFormat for CosignerStatus {
+    fn output_headers() -> Vec<String> {
+        vec![
+            s!("Fingerprint"),
+            s!("Signed inputs"),
+            s!("Inputs"),
+            s!("Status"),
+        ]
+    }
+
+    fn output_id_string(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
+        vec![
+            self.fingerprint.as_str().bright_white().to_string(),
+            self.signed.to_string(),
+            self.inputs.to_string(),
+            if self.is_complete() {
+                "signed".green().to_string()
+            } else if self.inputs == 0 {
+                "not involved".to_string()
+            } else if self.signed == 0 {
+                "missing".red().to_string()
+            } else {
+                "partial".yellow().to_string()
+            },
         ]
     }
 }
@@ -274,14 +785,14 @@ impl OutputCompact for UnhardenedIndex {
 
 impl OutputFormat for UnhardenedIndex {
     fn output_headers() -> Vec<String> {
-        vec![s!("ID"), s!("Index")]
+        vec![s!("Index")]
     }
 
     fn output_id_string(&self) -> String {
         self.to_string()
     }
 
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
         vec![self.to_string()]
     }
 }
@@ -309,7 +820,7 @@ impl OutputFormat for Utxo {
         ]
     }
 
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
         vec![
             self.value.to_string(),
             self.height.to_string(),
@@ -320,6 +831,280 @@ impl OutputFormat for Utxo {
     }
 }
 
+// MARK: PreciseUtxo -----------------------------------------------------------
+
+/// Unspent output accompanied with its amount formatted according to the
+/// precision of the asset it holds
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize)]
+pub struct PreciseUtxo {
+    #[serde(flatten)]
+    pub utxo: Utxo,
+
+    /// Amount with a decimal point and asset ticker
+    pub amount: String,
+}
+
+impl PreciseUtxo {
+    pub fn with(utxo: Utxo, precision: u8, ticker: &str) -> PreciseUtxo {
+        PreciseUtxo {
+            amount: util::format_amount(utxo.value, precision, ticker),
+            utxo,
+        }
+    }
+}
+
+impl OutputCompact for PreciseUtxo {
+    fn output_compact(&self) -> String {
+        format!("{}@{}", self.amount, self.utxo.output_compact())
+    }
+}
+
+impl OutputFormat for PreciseUtxo {
+    fn output_id_string(&self) -> String {
+        self.amount.clone()
+    }
+
+    fn output_headers() -> Vec<String> {
+        let mut vec = vec![s!("Amount")];
+        vec.extend(Utxo::output_headers());
+        vec
+    }
+
+    fn output_fields(&self, opts: &OutputOpts) -> Vec<String> {
+        let mut vec = vec![self.amount.as_str().bright_yellow().to_string()];
+        vec.extend(self.utxo.output_fields(opts));
+        vec
+    }
+}
+
+// MARK: CoinAllocation --------------------------------------------------------
+
+/// Unspent output with amounts of all assets allocated to it
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize)]
+pub struct CoinAllocation {
+    pub height: u32,
+    pub offset: u16,
+    pub vout: u16,
+    pub derivation_index: UnhardenedIndex,
+
+    /// Amounts (with decimal point and ticker) of the assets allocated to the
+    /// output, indexed by the asset id
+    pub assets: BTreeMap<String, String>,
+}
+
+impl CoinAllocation {
+    /// Regroups per-asset unspent outputs into per-output asset allocations.
+    ///
+    /// Outputs are identified by their block height, transaction offset and
+    /// output number; amounts of the same asset reported for a single output
+    /// are summed. Fails if the same output identifier is reported with
+    /// different derivation indexes, i.e. when two distinct outputs collide
+    /// and can't be told apart.
+    pub fn with_unspent(
+        unspent: HashMap<rgb::ContractId, Vec<Utxo>>,
+        precisions: &HashMap<rgb::ContractId, (String, u8)>,
+    ) -> Result<Vec<CoinAllocation>, String> {
+        let mut coins = BTreeMap::<
+            (u32, u16, u16),
+            (UnhardenedIndex, BTreeMap<rgb::ContractId, u64>),
+        >::new();
+        for (asset_id, utxos) in unspent {
+            for utxo in utxos {
+                let (derivation_index, values) = coins
+                    .entry((utxo.height, utxo.offset, utxo.vout))
+                    .or_insert_with(|| {
+                        (utxo.derivation_index, BTreeMap::new())
+                    });
+                if *derivation_index != utxo.derivation_index {
+                    return Err(format!(
+                        "unspent output {}:{}:{} is reported with different \
+                         derivation indexes {} and {}",
+                        utxo.height,
+                        utxo.offset,
+                        utxo.vout,
+                        derivation_index,
+                        utxo.derivation_index
+                    ));
+                }
+                let value = values.entry(asset_id).or_insert(0);
+                *value = value.checked_add(utxo.value).ok_or_else(|| {
+                    format!(
+                        "amount of asset {} allocated to unspent output \
+                         {}:{}:{} overflows",
+                        asset_id, utxo.height, utxo.offset, utxo.vout
+                    )
+                })?;
+            }
+        }
+        Ok(coins
+            .into_iter()
+            .map(|((height, offset, vout), (derivation_index, values))| {
+                CoinAllocation {
+                    height,
+                    offset,
+                    vout,
+                    derivation_index,
+                    assets: values
+                        .into_iter()
+                        .map(|(asset_id, value)| {
+                            let (ticker, precision) = precisions
+                                .get(&asset_id)
+                                .cloned()
+                                .unwrap_or((s!("?"), 0));
+                            (
+                                asset_id.to_string(),
+                                util::format_amount(value, precision, &ticker),
+                            )
+                        })
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+impl OutputCompact for CoinAllocation {
+    fn output_compact(&self) -> String {
+        format!(
+            "{}:{}:{}[{}]",
+            self.height,
+            self.offset,
+            self.vout,
+            self.assets.values().cloned().collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+impl OutputFormat for CoinAllocation {
+    fn output_id_string(&self) -> String {
+        format!("{}:{}:{}", self.height, self.offset, self.vout)
+    }
+
+    fn output_headers() -> Vec<String> {
+        vec![
+            s!("Block height"),
+            s!("Block tx offset"),
+            s!("Output no"),
+            s!("Derivation index"),
+            s!("Assets"),
+        ]
+    }
+
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
+        vec![
+            self.height.to_string(),
+            self.offset.to_string(),
+            self.vout.to_string(),
+            self.derivation_index.to_string(),
+            self.assets
+                .values()
+                .map(|amount| amount.as_str().bright_yellow().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ]
+    }
+}
+
+// MARK: PreciseOperation ------------------------------------------------------
+
+/// Wallet operation accompanied with its amounts formatted according to the
+/// precision of the transferred asset and bitcoin
+#[derive(Clone, Debug, Serialize)]
+pub struct PreciseOperation {
+    #[serde(flatten)]
+    pub operation: Operation,
+
+    /// Bitcoin amount with a decimal point and `BTC` ticker
+    pub bitcoin_amount: String,
+
+    /// Asset amount with a decimal point and asset ticker, if the operation
+    /// transfers an RGB asset
+    pub asset_amount: Option<String>,
+
+    /// Transaction fee with a decimal point and `BTC` ticker
+    pub fee: String,
+}
+
+impl PreciseOperation {
+    pub fn with(
+        operation: Operation,
+        precisions: &HashMap<rgb::ContractId, (String, u8)>,
+    ) -> PreciseOperation {
+        let asset_amount = operation.asset_id.map(|asset_id| {
+            let (ticker, precision) =
+                precisions.get(&asset_id).cloned().unwrap_or((s!("?"), 0));
+            util::format_amount(operation.asset_value, precision, &ticker)
+        });
+        PreciseOperation {
+            bitcoin_amount: util::format_amount(
+                operation.bitcoin_value,
+                8,
+                "BTC",
+            ),
+            fee: util::format_amount(operation.tx_fee, 8, "BTC"),
+            asset_amount,
+            operation,
+        }
+    }
+}
+
+impl OutputCompact for PreciseOperation {
+    fn output_compact(&self) -> String {
+        format!("{}@{}", self.output_id_string(), self.operation.height)
+    }
+}
+
+impl OutputFormat for PreciseOperation {
+    fn output_headers() -> Vec<String> {
+        vec![
+            s!("Txid"),
+            s!("Direction"),
+            s!("Asset"),
+            s!("Bitcoin value"),
+            s!("Asset value"),
+            s!("Fee"),
+            s!("Block height"),
+            s!("Created"),
+        ]
+    }
+
+    fn output_id_string(&self) -> String {
+        self.operation
+            .psbt
+            .as_inner()
+            .global
+            .unsigned_tx
+            .txid()
+            .to_string()
+    }
+
+    fn output_fields(&self, opts: &OutputOpts) -> Vec<String> {
+        vec![
+            self.output_id_string().as_str().bright_white().to_string(),
+            match self.operation.direction {
+                PaymentDirecton::Incoming { .. } => {
+                    "incoming".green().to_string()
+                }
+                PaymentDirecton::Outcoming { .. } => {
+                    "outgoing".yellow().to_string()
+                }
+            },
+            self.operation
+                .asset_id
+                .map(|asset_id| asset_id.to_string())
+                .unwrap_or_else(|| s!("-")),
+            self.bitcoin_amount.as_str().bright_yellow().to_string(),
+            self.asset_amount
+                .as_deref()
+                .map(|amount| amount.bright_yellow().to_string())
+                .unwrap_or_else(|| s!("-")),
+            self.fee.clone(),
+            self.operation.height.to_string(),
+            format_datetime(&self.operation.created_at, opts),
+        ]
+    }
+}
+
 // MARK: AddressDerivation -----------------------------------------------------
 
 impl OutputCompact for AddressDerivation {
@@ -337,7 +1122,7 @@ impl OutputFormat for AddressDerivation {
         self.address.to_string()
     }
 
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, _: &OutputOpts) -> Vec<String> {
         vec![
             self.address.to_string(),
             self.derivation
@@ -373,7 +1158,7 @@ impl OutputFormat for rgb20::Asset {
         self.id().to_string()
     }
 
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, opts: &OutputOpts) -> Vec<String> {
         let bitcoin_id = rgb::ContractId::from_inner(
             sha256t::Hash::from_inner(BITCOIN_GENESIS_BLOCKHASH.into()),
         );
@@ -382,7 +1167,11 @@ impl OutputFormat for rgb20::Asset {
                 s!("BTC").as_str().bright_yellow().to_string(),
                 s!("Bitcoin"),
                 bitcoin_id.to_string().as_str().bright_white().to_string(),
-                s!("2009-01-03 19:15:00"),
+                s!("8"),
+                format_datetime(
+                    &NaiveDate::from_ymd(2009, 1, 3).and_hms(18, 15, 5),
+                    opts,
+                ),
                 s!(">~18624337 BTC"),
                 s!("21000000 BTC"),
             ];
@@ -396,7 +1185,7 @@ impl OutputFormat for rgb20::Asset {
             self.name().to_owned(),
             self.id().to_string().as_str().bright_white().to_string(),
             self.decimal_precision().to_string(),
-            self.date().to_string(),
+            format_datetime(&self.date(), opts),
             self.precise_supply(rgb20::SupplyMeasure::KnownCirculating)
                 .map(|v| v.to_string())
                 .unwrap_or(s!("unknown")),
@@ -434,7 +1223,7 @@ impl OutputFormat for Invoice {
         self.to_string()
     }
 
-    fn output_fields(&self) -> Vec<String> {
+    fn output_fields(&self, opts: &OutputOpts) -> Vec<String> {
         vec![
             self.to_string().as_str().bright_white().to_string(),
             self.beneficiaries().count().to_string(),
@@ -451,10 +1240,133 @@ impl OutputFormat for Invoice {
             self.recurrent().to_string(),
             self.expiry()
                 .as_ref()
-                .map(NaiveDateTime::to_string)
+                .map(|expiry| format_datetime(expiry, opts))
                 .unwrap_or(s!("-")),
             self.merchant().clone().unwrap_or(s!("-")),
             self.purpose().clone().unwrap_or(s!("-")),
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{PublicKey, Transaction, TxIn};
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn psbt_fixture() -> Psbt {
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn::default(), TxIn::default()],
+            output: vec![],
+        };
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        let key_a = PublicKey::from_str(
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        )
+        .unwrap();
+        let key_b = PublicKey::from_str(
+            "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+        )
+        .unwrap();
+        for input in &mut psbt.inputs {
+            input
+                .bip32_derivation
+                .insert(key_a, (Fingerprint::from(&[0xaa; 4][..]), default!()));
+            input
+                .bip32_derivation
+                .insert(key_b, (Fingerprint::from(&[0xbb; 4][..]), default!()));
+        }
+        // First cosigner signed both inputs, second only the first one
+        psbt.inputs[0].partial_sigs.insert(key_a, vec![0x30]);
+        psbt.inputs[1].partial_sigs.insert(key_a, vec![0x30]);
+        psbt.inputs[0].partial_sigs.insert(key_b, vec![0x30]);
+        psbt
+    }
+
+    #[test]
+    fn cosigner_status() {
+        let psbt = psbt_fixture();
+        let status = CosignerStatus::with_psbt(
+            &psbt,
+            &[
+                Fingerprint::from(&[0xaa; 4][..]),
+                Fingerprint::from(&[0xbb; 4][..]),
+                Fingerprint::from(&[0xcc; 4][..]),
+            ],
+        );
+        assert_eq!(status[0].fingerprint, "aaaaaaaa");
+        assert_eq!((status[0].signed, status[0].inputs), (2, 2));
+        assert!(status[0].is_complete());
+        assert_eq!((status[1].signed, status[1].inputs), (1, 2));
+        assert!(!status[1].is_complete());
+        assert_eq!((status[2].signed, status[2].inputs), (0, 0));
+        assert!(!status[2].is_complete());
+    }
+
+    fn utxo(
+        value: u64,
+        height: u32,
+        offset: u16,
+        vout: u16,
+        index: u16,
+    ) -> Utxo {
+        Utxo {
+            value,
+            height,
+            offset,
+            vout,
+            derivation_index: UnhardenedIndex::from(index),
+        }
+    }
+
+    #[test]
+    fn coin_allocation() {
+        let bitcoin_id: rgb::ContractId = default!();
+        let asset_id =
+            rgb::ContractId::from_inner(sha256t::Hash::from_inner([1u8; 32]));
+        let mut precisions = HashMap::new();
+        precisions.insert(bitcoin_id, (s!("BTC"), 8));
+        precisions.insert(asset_id, (s!("USDT"), 2));
+
+        let mut unspent = HashMap::new();
+        unspent.insert(
+            bitcoin_id,
+            vec![utxo(1000, 100, 1, 0, 3), utxo(500, 100, 1, 0, 3)],
+        );
+        unspent.insert(
+            asset_id,
+            vec![utxo(150, 100, 1, 0, 3), utxo(7, 100, 2, 1, 4)],
+        );
+
+        let coins = CoinAllocation::with_unspent(unspent, &precisions).unwrap();
+        assert_eq!(coins.len(), 2);
+        assert_eq!(
+            (coins[0].height, coins[0].offset, coins[0].vout),
+            (100, 1, 0)
+        );
+        assert_eq!(coins[0].derivation_index, UnhardenedIndex::from(3u16));
+        assert_eq!(coins[0].assets.len(), 2);
+        assert_eq!(coins[0].assets[&bitcoin_id.to_string()], "0.00001500 BTC");
+        assert_eq!(coins[0].assets[&asset_id.to_string()], "1.50 USDT");
+        assert_eq!(
+            (coins[1].height, coins[1].offset, coins[1].vout),
+            (100, 2, 1)
+        );
+        assert_eq!(coins[1].assets.len(), 1);
+        assert_eq!(coins[1].assets[&asset_id.to_string()], "0.07 USDT");
+    }
+
+    #[test]
+    fn coin_allocation_collision() {
+        let asset_id: rgb::ContractId = default!();
+        let mut unspent = HashMap::new();
+        unspent.insert(
+            asset_id,
+            vec![utxo(1000, 0, 0, 0, 3), utxo(2000, 0, 0, 0, 5)],
+        );
+        assert!(CoinAllocation::with_unspent(unspent, &HashMap::new()).is_err());
+    }
+}
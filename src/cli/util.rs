@@ -12,19 +12,80 @@
 // If not, see <https://www.gnu.org/licenses/agpl-3.0-standalone.html>.
 
 use colored::Colorize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::str::FromStr;
 
+use amplify::Wrapper;
 use base64::display::Base64Display;
-use bitcoin::consensus::{serialize, Encodable};
-use bitcoin::hashes::hex::ToHex;
+use bitcoin::consensus::{deserialize, serialize, Encodable};
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::{sha256t, Hash};
+use bitcoin::util::bip32::ExtendedPubKey;
+use invoice::Beneficiary;
+use miniscript::policy::Liftable;
+use miniscript::{Descriptor, DescriptorType, MiniscriptKey};
+use rgb::Consignment;
+use strict_encoding::StrictDecode;
+use wallet::blockchain::BITCOIN_GENESIS_BLOCKHASH;
+use wallet::descriptors::ContentType;
+use wallet::hd::{PubkeyChain, XpubRef};
 use wallet::psbt::Psbt;
 
-use citadel::Error;
+use citadel::{Error, SECP256K1};
 
 use super::PsbtFormat;
 
+const DESCRIPTOR_INPUT_CHARSET: &'static str = concat!(
+    "0123456789()[],'/*abcdefgh@:$%{}",
+    "IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~",
+    "ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ",
+);
+const DESCRIPTOR_CHECKSUM_CHARSET: &'static [u8] =
+    b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Detects whether the string is a non-empty even-length hexadecimal data
+fn is_hex(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() % 2 == 0
+        && s.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Parses PSBT provided either as Base64 or hexadecimal string
+pub(super) fn parse_psbt(psbt: &str) -> Result<Psbt, Error> {
+    let psbt = psbt.trim();
+    let data = if is_hex(psbt) {
+        Vec::<u8>::from_hex(psbt).expect("hex string is checked")
+    } else {
+        base64::decode(psbt)?
+    };
+    Ok(deserialize(&data)?)
+}
+
+/// Parses consignment provided as Bech32 string, hexadecimal string or raw
+/// strict-encoded binary data
+pub(super) fn parse_consignment(data: &[u8]) -> Result<Consignment, String> {
+    if let Ok(s) = std::str::from_utf8(data) {
+        let s = s.trim();
+        if is_hex(s) {
+            let data = Vec::<u8>::from_hex(s).expect("hex string is checked");
+            return Consignment::strict_deserialize(&data)
+                .map_err(|err| format!("invalid hex consignment: {}", err));
+        }
+        if let Ok(consignment) = Consignment::from_str(s) {
+            return Ok(consignment);
+        }
+    }
+    Consignment::strict_deserialize(data).map_err(|err| {
+        format!(
+            "consignment is neither Bech32, hex nor binary-encoded: {}",
+            err
+        )
+    })
+}
+
 pub(super) fn psbt_output(
     psbt: &Psbt,
     output: Option<PathBuf>,
@@ -71,3 +132,818 @@ pub(super) fn psbt_output(
 
     Ok(())
 }
+
+/// Formats atomic amount value with a decimal point placed according to the
+/// asset precision, followed by the asset ticker
+pub(super) fn format_amount(value: u64, precision: u8, ticker: &str) -> String {
+    if precision == 0 {
+        return format!("{} {}", value, ticker);
+    }
+    let divisor = 10u128.pow(precision.min(38) as u32);
+    let value = value as u128;
+    format!(
+        "{}.{:0width$} {}",
+        value / divisor,
+        value % divisor,
+        ticker,
+        width = precision as usize
+    )
+}
+
+/// Returns minimal number of signatures required to satisfy the wallet
+/// descriptor, or `None` if the descriptor can't be analyzed
+pub(super) fn signature_threshold(descriptor: &str) -> Option<usize> {
+    let descriptor = descriptor
+        .split_once('#')
+        .map(|(descriptor, _)| descriptor)
+        .unwrap_or(descriptor);
+    Descriptor::<String>::from_str(descriptor)
+        .ok()?
+        .lift()
+        .ok()
+        .map(|policy| policy.minimum_n_keys())
+}
+
+/// Prints to STDERR per-input signing status of the PSBT against the number
+/// of signatures required by the wallet descriptor and whether all of the
+/// inputs are satisfied, such that the PSBT can be finalized and published
+pub(super) fn psbt_signing_report(psbt: &Psbt, descriptor: &str) {
+    let threshold = signature_threshold(descriptor);
+    let mut missing = 0usize;
+
+    eprintln!("{}", "Signing status:".bright_yellow());
+    for (no, input) in psbt.inputs.iter().enumerate() {
+        let signed = input.partial_sigs.len();
+        if input.final_script_sig.is_some()
+            || input.final_script_witness.is_some()
+        {
+            eprintln!("- input #{}: {}", no, "finalized".green());
+        } else if let Some(threshold) = threshold {
+            let needs = threshold.saturating_sub(signed);
+            missing += needs;
+            if needs == 0 {
+                eprintln!(
+                    "- input #{}: {} of {} required signatures",
+                    no,
+                    signed.to_string().green(),
+                    threshold
+                );
+            } else {
+                eprintln!(
+                    "- input #{}: {} of {} required signatures, needs {} more",
+                    no,
+                    signed.to_string().yellow(),
+                    threshold,
+                    needs.to_string().yellow()
+                );
+            }
+        } else {
+            eprintln!("- input #{}: {} signatures", no, signed);
+        }
+    }
+
+    if missing > 0 {
+        eprintln!(
+            "{} {} more signature(s)",
+            "PSBT needs".bright_yellow(),
+            missing.to_string().yellow()
+        );
+        return;
+    }
+
+    let mut finalized = psbt.clone();
+    match miniscript::psbt::finalize(&mut finalized, &*SECP256K1) {
+        Ok(_) => eprintln!(
+            "{}",
+            "PSBT is fully signed - ready to publish".bright_green()
+        ),
+        Err(err) if threshold.is_some() => eprintln!(
+            "{} {}",
+            "PSBT has all required signatures, but can't be finalized:"
+                .bright_red(),
+            err.to_string().red()
+        ),
+        Err(err) => eprintln!(
+            "{} {}",
+            "PSBT can't be finalized:".bright_yellow(),
+            err.to_string().yellow()
+        ),
+    }
+}
+
+/// Converts public key generator into BIP-380 key expressions, like
+/// `[6734cda8/84h/0h/1h]xpub.../0/*`. Generators with `0-1` terminal range
+/// are converted into a pair of expressions for the receiving (`0`) and
+/// change (`1`) branches; other ranges can't be expressed in BIP-380 form.
+fn bip380_keys(pubkey_chain: &PubkeyChain) -> Result<Vec<String>, String> {
+    let origin = match pubkey_chain.master {
+        XpubRef::Unknown => s!(""),
+        _ => format!(
+            "[{}{}]",
+            pubkey_chain.master_fingerprint(),
+            pubkey_chain
+                .source_path
+                .iter()
+                .map(ToString::to_string)
+                .chain(std::iter::once(pubkey_chain.branch_index.to_string()))
+                .map(|index| format!("/{}", index.replace('\'', "h")))
+                .collect::<String>()
+        ),
+    };
+
+    let terminal = pubkey_chain
+        .terminal_path
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let ranges = terminal
+        .iter()
+        .enumerate()
+        .filter(|(_, step)| step.contains(|c| c == '-' || c == ','))
+        .collect::<Vec<_>>();
+    let terminals = match ranges.as_slice() {
+        [] => vec![terminal.clone()],
+        [(pos, range)] if range.as_str() == "0-1" => (0..=1)
+            .map(|branch| {
+                let mut terminal = terminal.clone();
+                terminal[*pos] = branch.to_string();
+                terminal
+            })
+            .collect(),
+        _ => {
+            return Err(format!(
+                "key {} uses derivation range which can't be expressed as \
+                 BIP-380 descriptor",
+                pubkey_chain
+            ))
+        }
+    };
+
+    Ok(terminals
+        .into_iter()
+        .map(|terminal| {
+            format!(
+                "{}{}{}",
+                origin,
+                pubkey_chain.branch_xpub,
+                terminal
+                    .iter()
+                    .map(|step| format!("/{}", step))
+                    .collect::<String>()
+            )
+        })
+        .collect())
+}
+
+/// Converts wallet descriptor, which uses public key generators as keys, into
+/// BIP-380 descriptors (without checksum). If the keys use `0-1` terminal
+/// range, two descriptors are returned: for the receiving (`0`) and change
+/// (`1`) derivation branches; otherwise a single descriptor is returned.
+pub(super) fn bip380_descriptors(
+    descriptor: &str,
+    pubkey_chains: &[PubkeyChain],
+) -> Result<Vec<String>, String> {
+    let descriptor = descriptor
+        .split_once('#')
+        .map(|(descriptor, _)| descriptor)
+        .unwrap_or(descriptor);
+    let keys = pubkey_chains
+        .iter()
+        .map(|pubkey_chain| {
+            bip380_keys(pubkey_chain).map(|keys| (pubkey_chain, keys))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = keys.iter().map(|(_, keys)| keys.len()).max().unwrap_or(1);
+    Ok((0..count)
+        .map(|no| {
+            keys.iter().fold(
+                descriptor.to_owned(),
+                |descriptor, (pubkey_chain, keys)| {
+                    // Keys without the branch range are the same for both
+                    // receiving and change descriptors
+                    let key = keys.get(no).unwrap_or(&keys[0]);
+                    descriptor.replace(&pubkey_chain.to_string(), key)
+                },
+            )
+        })
+        .collect())
+}
+
+fn descriptor_poly_mod(mut c: u64, val: u64) -> u64 {
+    let c0 = c >> 35;
+    c = ((c & 0x7ffffffff) << 5) ^ val;
+    if c0 & 1 != 0 {
+        c ^= 0xf5dee51989
+    }
+    if c0 & 2 != 0 {
+        c ^= 0xa9fdca3312
+    }
+    if c0 & 4 != 0 {
+        c ^= 0x1bab10e32d
+    }
+    if c0 & 8 != 0 {
+        c ^= 0x3706b1677a
+    }
+    if c0 & 16 != 0 {
+        c ^= 0x644d626ffd
+    }
+    c
+}
+
+/// Computes BIP-380 output descriptor checksum. Returns `None` if the
+/// descriptor contains characters not allowed by BIP-380.
+pub(super) fn descriptor_checksum(descriptor: &str) -> Option<String> {
+    let mut c = 1u64;
+    let mut cls = 0u64;
+    let mut cls_count = 0u8;
+    for ch in descriptor.chars() {
+        let pos = DESCRIPTOR_INPUT_CHARSET.find(ch)? as u64;
+        c = descriptor_poly_mod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        cls_count += 1;
+        if cls_count == 3 {
+            c = descriptor_poly_mod(c, cls);
+            cls = 0;
+            cls_count = 0;
+        }
+    }
+    if cls_count > 0 {
+        c = descriptor_poly_mod(c, cls);
+    }
+    (0..8).for_each(|_| c = descriptor_poly_mod(c, 0));
+    c ^= 1;
+    Some(
+        (0..8)
+            .map(|j| (c >> (5 * (7 - j))) & 31)
+            .map(|i| DESCRIPTOR_CHECKSUM_CHARSET[i as usize] as char)
+            .collect(),
+    )
+}
+
+/// Returns minimal non-dust value (in satoshis) for the outputs generated by
+/// the descriptor, according to Bitcoin Core default relay policy
+pub(super) fn dust_limit<Pk>(descriptor: &Descriptor<Pk>) -> u64
+where
+    Pk: MiniscriptKey,
+{
+    match descriptor.desc_type() {
+        DescriptorType::Wpkh => 294,
+        DescriptorType::Wsh | DescriptorType::WshSortedMulti => 330,
+        _ => 546,
+    }
+}
+
+/// Returns contract id under which bitcoin is represented among RGB assets
+pub(super) fn bitcoin_asset_id() -> rgb::ContractId {
+    rgb::ContractId::from_inner(sha256t::Hash::from_inner(
+        BITCOIN_GENESIS_BLOCKHASH.into(),
+    ))
+}
+
+/// Parses YAML document mapping asset ids to exchange rates. Assets must be
+/// identified by their contract id, since tickers are not unique; the only
+/// allowed ticker is `BTC`, which stands for bitcoin.
+pub(super) fn parse_rates(
+    yaml: &str,
+) -> Result<BTreeMap<rgb::ContractId, f64>, String> {
+    let mut rates = BTreeMap::new();
+    for (key, rate) in serde_yaml::from_str::<BTreeMap<String, f64>>(yaml)
+        .map_err(|err| err.to_string())?
+    {
+        let asset_id = if key == "BTC" {
+            bitcoin_asset_id()
+        } else {
+            rgb::ContractId::from_str(&key).map_err(|_| {
+                format!(
+                    "exchange rate key `{}` is not an asset id; assets other \
+                     than BTC must be identified by their contract id, since \
+                     tickers are ambiguous",
+                    key
+                )
+            })?
+        };
+        if rates.insert(asset_id, rate).is_some() {
+            return Err(format!(
+                "exchange rate for asset {} is given more than once",
+                asset_id
+            ));
+        }
+    }
+    Ok(rates)
+}
+
+/// Checks that the giveaway amount (in satoshis) is given only for
+/// descriptor-based RGB invoices, where it is required and must not be below
+/// the dust limit of the beneficiary descriptor
+pub(super) fn check_giveaway(
+    beneficiary: &Beneficiary,
+    asset: Option<rgb::ContractId>,
+    giveaway: Option<u64>,
+) -> Result<(), String> {
+    match (beneficiary, asset, giveaway) {
+        (Beneficiary::Descriptor(descriptor), Some(_), None) => Err(format!(
+            "paying descriptor-based RGB invoice requires giveaway amount of \
+             at least {} sats",
+            dust_limit(descriptor)
+        )),
+        (Beneficiary::Descriptor(descriptor), Some(_), Some(giveaway))
+            if giveaway < dust_limit(descriptor) =>
+        {
+            Err(format!(
+                "giveaway of {} sats is below the dust limit; use at least {} \
+                 sats",
+                giveaway,
+                dust_limit(descriptor)
+            ))
+        }
+        (Beneficiary::Descriptor(_), Some(_), Some(_)) => Ok(()),
+        (_, _, Some(_)) => Err(s!(
+            "giveaway is allowed only when paying descriptor-based RGB invoices"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Single-key descriptor, as exported by Bitcoin Core, split into parts
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct CoreDescriptor {
+    category: ContentType,
+    nested: bool,
+    origin: Option<(String, String)>,
+    xpub: String,
+    branch: Option<u32>,
+}
+
+fn parse_core_descriptor(descriptor: &str) -> Result<CoreDescriptor, String> {
+    let descriptor = descriptor.trim();
+    let body = match descriptor.split_once('#') {
+        None => descriptor,
+        Some((body, checksum)) => {
+            if descriptor_checksum(body).as_deref() != Some(checksum) {
+                return Err(format!(
+                    "invalid checksum in descriptor `{}`",
+                    descriptor
+                ));
+            }
+            body
+        }
+    };
+
+    if body.starts_with("tr(") {
+        return Err(format!(
+            "taproot descriptor `{}` can't be imported: taproot wallets are \
+             reserved for the future",
+            body
+        ));
+    }
+
+    let (category, nested, key) = [
+        ("sh(wpkh(", "))", ContentType::SegWit, true),
+        ("wpkh(", ")", ContentType::SegWit, false),
+        ("pkh(", ")", ContentType::Hashed, false),
+        ("pk(", ")", ContentType::Bare, false),
+    ]
+    .iter()
+    .find_map(|(prefix, suffix, category, nested)| {
+        body.strip_prefix(prefix)
+            .and_then(|key| key.strip_suffix(suffix))
+            .map(|key| (*category, *nested, key))
+    })
+    .ok_or_else(|| {
+        format!(
+            "unsupported descriptor `{}`; only single-key `pk`, `pkh`, \
+             `wpkh` and `sh(wpkh)` descriptors can be imported",
+            body
+        )
+    })?;
+
+    let (origin, key) = match key.strip_prefix('[') {
+        None => (None, key),
+        Some(key) => {
+            let (origin, key) = key.split_once(']').ok_or_else(|| {
+                format!("unterminated key origin in `{}`", body)
+            })?;
+            let (fingerprint, path) =
+                origin.split_once('/').unwrap_or((origin, ""));
+            let path = path.replace('h', "'").replace('H', "'");
+            (Some((fingerprint.to_lowercase(), path)), key)
+        }
+    };
+
+    let mut segments = key.split('/');
+    let xpub = segments.next().unwrap_or_default().to_owned();
+    let segments = segments.collect::<Vec<_>>();
+    let branch = match segments.as_slice() {
+        ["*"] => None,
+        [branch, "*"] => Some(branch.parse::<u32>().map_err(|_| {
+            format!("hardened or invalid branch index in `{}`", body)
+        })?),
+        _ => {
+            return Err(format!(
+                "descriptor `{}` must use ranged key ending with `/*`",
+                body
+            ))
+        }
+    };
+
+    Ok(CoreDescriptor {
+        category,
+        nested,
+        origin,
+        xpub,
+        branch,
+    })
+}
+
+/// Wallet account imported from Bitcoin Core descriptors
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(super) struct CoreAccount {
+    /// Wallet descriptor category
+    pub category: ContentType,
+    /// Whether the account uses segwit nested into P2SH (`sh(wpkh(...))`),
+    /// requiring `--legacy` flag for address commands
+    pub nested: bool,
+    /// Public key generator string
+    pub pubkey_chain: String,
+}
+
+/// Converts Bitcoin Core single-key output descriptors (like the output of
+/// `listdescriptors` command) into wallet accounts. Descriptors sharing the
+/// same script type and account key (usually a pair of receiving and change
+/// descriptors) are combined into a single account; accounts are returned in
+/// the order of their first descriptor.
+pub(super) fn core_descriptors_accounts(
+    descriptors: &[String],
+) -> Result<Vec<CoreAccount>, String> {
+    let mut groups: Vec<Vec<CoreDescriptor>> = vec![];
+    for descriptor in descriptors {
+        let descriptor = parse_core_descriptor(descriptor)?;
+        match groups.iter_mut().find(|group| {
+            group[0].category == descriptor.category
+                && group[0].nested == descriptor.nested
+                && group[0].origin == descriptor.origin
+                && group[0].xpub == descriptor.xpub
+        }) {
+            Some(group) => group.push(descriptor),
+            None => groups.push(vec![descriptor]),
+        }
+    }
+    if groups.is_empty() {
+        return Err(s!("no descriptors provided"));
+    }
+    groups.iter().map(|group| core_account(group)).collect()
+}
+
+/// Combines descriptors sharing the same script type and account key into a
+/// wallet account
+fn core_account(descriptors: &[CoreDescriptor]) -> Result<CoreAccount, String> {
+    let first = &descriptors[0];
+    let xpub = ExtendedPubKey::from_str(&first.xpub).map_err(|err| {
+        format!("invalid extended public key {}: {}", first.xpub, err)
+    })?;
+    let branches = descriptors
+        .iter()
+        .map(|descriptor| descriptor.branch)
+        .collect::<Option<BTreeSet<_>>>();
+    let terminal = match branches {
+        None if descriptors.len() == 1 => s!("*"),
+        None => {
+            return Err(format!(
+                "descriptors for key {} without branch index can't be \
+                 combined with others",
+                first.xpub
+            ))
+        }
+        Some(branches) => {
+            let min = *branches.iter().next().expect("non-empty set");
+            let max = *branches.iter().last().expect("non-empty set");
+            if (max - min + 1) as usize != branches.len() {
+                return Err(format!(
+                    "branch indexes of descriptors for key {} must form a \
+                     continuous range",
+                    first.xpub
+                ));
+            }
+            if min == max {
+                format!("{}/*", min)
+            } else {
+                format!("{}-{}/*", min, max)
+            }
+        }
+    };
+
+    let pubkey_chain = match &first.origin {
+        Some((fingerprint, path)) if !path.is_empty() => format!(
+            "m=[{}]/{}=[{}]/{}",
+            fingerprint, path, first.xpub, terminal
+        ),
+        // Key without origin derivation path is a master key itself, so its
+        // own fingerprint must match the origin master fingerprint
+        Some((fingerprint, _)) => {
+            if xpub.fingerprint().to_string() != *fingerprint {
+                return Err(format!(
+                    "key origin fingerprint {} does not match fingerprint {} \
+                     of the key {}, while the origin has no derivation path",
+                    fingerprint,
+                    xpub.fingerprint(),
+                    first.xpub
+                ));
+            }
+            format!("[{}]/{}", first.xpub, terminal)
+        }
+        None => format!("[{}]/{}", first.xpub, terminal),
+    };
+    Ok(CoreAccount {
+        category: first.category,
+        nested: first.nested,
+        pubkey_chain,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use miniscript::descriptor::DescriptorPublicKey;
+    use strict_encoding::StrictEncode;
+
+    use super::*;
+
+    // Example descriptor from Bitcoin Core `doc/descriptors.md`
+    const CORE_XPUB: &'static str = "xpub6DJ2dNUysrn5Vt36jH2KLBT2i1auw1tTSSomg8PhqNiUtx8QX2SvC9nrHu81fT41fvDUnhMjEzQgXnQjKEu3oaqMSzhSrHMxyyoEAmUHQbY";
+
+    #[test]
+    fn descriptor_checksum_vectors() {
+        assert_eq!(descriptor_checksum("raw(deadbeef)"), Some(s!("89f8spxm")));
+        assert_eq!(
+            descriptor_checksum(&format!(
+                "wpkh([d34db33f/84h/0h/0h]{}/0/*)",
+                CORE_XPUB
+            )),
+            Some(s!("cjjspncu"))
+        );
+        assert_eq!(descriptor_checksum("raw(deadbeef)\u{e9}"), None);
+    }
+
+    #[test]
+    fn signature_threshold_from_descriptor() {
+        assert_eq!(
+            signature_threshold(&format!("wpkh({})", CORE_XPUB)),
+            Some(1)
+        );
+        assert_eq!(
+            signature_threshold("wsh(sortedmulti(2,key_a,key_b,key_c))"),
+            Some(2)
+        );
+        assert_eq!(signature_threshold("wsh(sortedmulti(2,key_a"), None);
+    }
+
+    #[test]
+    fn rates_keyed_by_asset_id() {
+        let asset_id =
+            rgb::ContractId::from_inner(sha256t::Hash::from_inner([1u8; 32]));
+        let rates =
+            parse_rates(&format!("BTC: 1.0\n{}: 0.5\n", asset_id)).unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[&bitcoin_asset_id()], 1.0);
+        assert_eq!(rates[&asset_id], 0.5);
+
+        // Tickers other than BTC are ambiguous
+        assert!(parse_rates("USDT: 0.00003\n").is_err());
+        // BTC given both by ticker and asset id
+        assert!(parse_rates(&format!(
+            "BTC: 1.0\n{}: 1.0\n",
+            bitcoin_asset_id()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn giveaway_limits() {
+        let descriptor = Beneficiary::Descriptor(
+            Descriptor::<DescriptorPublicKey>::from_str(
+                "wpkh(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)",
+            )
+            .unwrap(),
+        );
+        let address = Beneficiary::Address(
+            bitcoin::Address::from_str(
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            )
+            .unwrap(),
+        );
+        let asset = Some(rgb::ContractId::default());
+
+        // Boundary value equal to the P2WPKH dust limit is accepted
+        assert_eq!(check_giveaway(&descriptor, asset, Some(294)), Ok(()));
+        assert_eq!(check_giveaway(&descriptor, asset, Some(10_000)), Ok(()));
+        // Giveaway below the dust limit or missing is rejected
+        assert!(check_giveaway(&descriptor, asset, Some(293)).is_err());
+        assert!(check_giveaway(&descriptor, asset, None).is_err());
+        // Giveaway can't go to non-descriptor beneficiaries or bitcoin
+        // payments
+        assert!(check_giveaway(&address, asset, Some(294)).is_err());
+        assert!(check_giveaway(&descriptor, None, Some(294)).is_err());
+        assert_eq!(check_giveaway(&address, asset, None), Ok(()));
+        assert_eq!(check_giveaway(&descriptor, None, None), Ok(()));
+    }
+
+    #[test]
+    fn bip380_descriptors_from_pubkey_chain() {
+        let pubkey_chain = PubkeyChain::from_str(&format!(
+            "m=[d34db33f]/84'/0'/0'=[{}]/0-1/*",
+            CORE_XPUB
+        ))
+        .unwrap();
+        let descriptors = bip380_descriptors(
+            &format!("wpkh({})#00000000", pubkey_chain),
+            &[pubkey_chain],
+        )
+        .unwrap();
+        assert_eq!(
+            descriptors,
+            vec![
+                format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)", CORE_XPUB),
+                format!("wpkh([d34db33f/84h/0h/0h]{}/1/*)", CORE_XPUB)
+            ]
+        );
+        assert_eq!(descriptor_checksum(&descriptors[0]), Some(s!("cjjspncu")));
+    }
+
+    #[test]
+    fn bip380_descriptors_without_origin() {
+        let pubkey_chain =
+            PubkeyChain::from_str(&format!("[{}]/0-1/*", CORE_XPUB)).unwrap();
+        let descriptors = bip380_descriptors(
+            &format!("pkh({})", pubkey_chain),
+            &[pubkey_chain],
+        )
+        .unwrap();
+        assert_eq!(descriptors[0], format!("pkh({}/0/*)", CORE_XPUB));
+    }
+
+    #[test]
+    fn bip380_descriptors_unbranched() {
+        let pubkey_chain = PubkeyChain::from_str(&format!(
+            "m=[d34db33f]/84'/0'/0'=[{}]/*",
+            CORE_XPUB
+        ))
+        .unwrap();
+        let descriptors = bip380_descriptors(
+            &format!("wpkh({})", pubkey_chain),
+            &[pubkey_chain],
+        )
+        .unwrap();
+        assert_eq!(
+            descriptors,
+            vec![format!("wpkh([d34db33f/84h/0h/0h]{}/*)", CORE_XPUB)]
+        );
+
+        let pubkey_chain =
+            PubkeyChain::from_str(&format!("[{}]/5/*", CORE_XPUB)).unwrap();
+        let descriptors = bip380_descriptors(
+            &format!("wpkh({})", pubkey_chain),
+            &[pubkey_chain],
+        )
+        .unwrap();
+        assert_eq!(descriptors, vec![format!("wpkh({}/5/*)", CORE_XPUB)]);
+    }
+
+    #[test]
+    fn bip380_descriptors_unsupported_range() {
+        let pubkey_chain =
+            PubkeyChain::from_str(&format!("[{}]/0-2/*", CORE_XPUB)).unwrap();
+        assert!(bip380_descriptors(
+            &format!("wpkh({})", pubkey_chain),
+            &[pubkey_chain]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn core_descriptors_grouped_by_account() {
+        let descriptors = [
+            ("pkh", "44h", "pkh(", ")"),
+            ("sh-wpkh", "49h", "sh(wpkh(", "))"),
+            ("wpkh", "84h", "wpkh(", ")"),
+        ]
+        .iter()
+        .flat_map(|(_, purpose, prefix, suffix)| {
+            (0..=1).map(move |branch| {
+                let descriptor = format!(
+                    "{}[d34db33f/{}/0h/0h]{}/{}/*{}",
+                    prefix, purpose, CORE_XPUB, branch, suffix
+                );
+                let checksum = descriptor_checksum(&descriptor).unwrap();
+                format!("{}#{}", descriptor, checksum)
+            })
+        })
+        .collect::<Vec<_>>();
+        let accounts = core_descriptors_accounts(&descriptors).unwrap();
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(accounts[0].category, ContentType::Hashed);
+        assert_eq!(
+            accounts[0].pubkey_chain,
+            format!("m=[d34db33f]/44'/0'/0'=[{}]/0-1/*", CORE_XPUB)
+        );
+        assert_eq!(accounts[1].category, ContentType::SegWit);
+        assert!(accounts[1].nested);
+        assert_eq!(accounts[2].category, ContentType::SegWit);
+        assert!(!accounts[2].nested);
+        assert_eq!(
+            accounts[2].pubkey_chain,
+            format!("m=[d34db33f]/84'/0'/0'=[{}]/0-1/*", CORE_XPUB)
+        );
+    }
+
+    #[test]
+    fn core_descriptors_taproot_rejected() {
+        let descriptors = [
+            format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)", CORE_XPUB),
+            format!("tr([d34db33f/86h/0h/0h]{}/0/*)", CORE_XPUB),
+        ];
+        assert!(core_descriptors_accounts(&descriptors).is_err());
+    }
+
+    #[test]
+    fn core_descriptors_fingerprint_only_origin() {
+        // Fingerprint of `CORE_XPUB` key itself
+        let descriptors = [format!("wpkh([c82ebd7c]{}/0/*)", CORE_XPUB)];
+        let accounts = core_descriptors_accounts(&descriptors).unwrap();
+        assert_eq!(accounts[0].pubkey_chain, format!("[{}]/0/*", CORE_XPUB));
+
+        let descriptors = [format!("wpkh([d34db33f]{}/0/*)", CORE_XPUB)];
+        assert!(core_descriptors_accounts(&descriptors).is_err());
+    }
+
+    #[test]
+    fn core_descriptors_corrupted() {
+        let descriptor = format!("wpkh([d34db33f/84h/0h/0h]{}/0/*)", CORE_XPUB);
+        assert!(core_descriptors_accounts(&[format!(
+            "{}#cjjspncu",
+            descriptor
+        )])
+        .is_ok());
+        assert!(core_descriptors_accounts(&[format!(
+            "{}#cjjspncv",
+            descriptor
+        )])
+        .is_err());
+
+        // Single character of the key is changed, such that its base58
+        // checksum does not match, while descriptor checksum is recomputed
+        let corrupted = descriptor.replace("xpub6DJ2", "xpub6DJ3");
+        let checksum = descriptor_checksum(&corrupted).unwrap();
+        assert!(core_descriptors_accounts(&[corrupted.clone()]).is_err());
+        assert!(core_descriptors_accounts(&[format!(
+            "{}#{}",
+            corrupted, checksum
+        )])
+        .is_err());
+    }
+
+    // Minimal PSBT with a single input and output and no other data
+    const PSBT_HEX: &'static str = "70736274ff010052020000000100000000000000000000000000000000000000000000000000000000000000000000000000ffffffff01e803000000000000160014000102030405060708090a0b0c0d0e0f1011121300000000000000";
+    const PSBT_BASE64: &'static str = "cHNidP8BAFICAAAAAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD/////AegDAAAAAAAAFgAUAAECAwQFBgcICQoLDA0ODxAREhMAAAAAAAAA";
+
+    #[test]
+    fn psbt_encodings() {
+        let psbt = parse_psbt(PSBT_BASE64).unwrap();
+        assert_eq!(psbt.global.unsigned_tx.output[0].value, 1000);
+        assert_eq!(serialize(&psbt).to_hex(), PSBT_HEX);
+        assert_eq!(parse_psbt(PSBT_HEX).unwrap(), psbt);
+        assert_eq!(parse_psbt(&format!(" {}\n", PSBT_HEX)).unwrap(), psbt);
+        assert!(parse_psbt("cHNidP8BAFICAAAAAQ").is_err());
+        assert!(parse_psbt("").is_err());
+    }
+
+    fn consignment() -> Consignment {
+        let genesis = rgb::Genesis::with(
+            rgb20::schema::schema().schema_id(),
+            lnpbp::Chain::Mainnet,
+            default!(),
+            default!(),
+            default!(),
+        );
+        Consignment::with(genesis, vec![], vec![], vec![])
+    }
+
+    #[test]
+    fn consignment_encodings() {
+        let consignment = consignment();
+        let id = consignment.to_string();
+        let binary = consignment.strict_serialize().unwrap();
+
+        let parsed = parse_consignment(id.as_bytes()).unwrap();
+        assert_eq!(parsed.to_string(), id);
+        let parsed = parse_consignment(&binary).unwrap();
+        assert_eq!(parsed.to_string(), id);
+        let parsed = parse_consignment(binary.to_hex().as_bytes()).unwrap();
+        assert_eq!(parsed.to_string(), id);
+        let parsed =
+            parse_consignment(format!("{}\n", binary.to_hex()).as_bytes())
+                .unwrap();
+        assert_eq!(parsed.to_string(), id);
+
+        assert!(parse_consignment(b"").is_err());
+        assert!(parse_consignment(&binary[..binary.len() / 2]).is_err());
+    }
+}
@@ -95,6 +95,8 @@ pub struct Opts {
     #[clap(long, default_value = MYCITADEL_RGB20_ENDPOINT, env = "MYCITADEL_RGB20_ENDPOINT")]
     pub rgb20_endpoint: ZmqSocketAddr,
 
+    /// Run RGB node inside the daemon process instead of connecting to an
+    /// external one at `--rgb20-endpoint`
     #[clap(long)]
     pub rgb_embedded: bool,
 
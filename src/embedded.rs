@@ -29,6 +29,10 @@ pub struct Opts {
     #[clap(flatten)]
     pub daemon: crate::daemon::Opts,
 
+    /// Output formatting options
+    #[clap(flatten)]
+    pub output: crate::cli::OutputOpts,
+
     /// Command to execute
     #[clap(subcommand)]
     pub command: Command,